@@ -0,0 +1,61 @@
+// Copyright 2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+//! Compare the masked hash search for near-duplicate box IDs with the pairwise search.
+//!
+//! ```bash
+//! $ cargo bench --bench y2018_day2
+//! ```
+
+extern crate advent;
+
+use advent::y2018::day2::{part2, part2_pairwise, read};
+use std::time::{Duration, Instant};
+
+/// Run the function enough times to get a stable measure and report the mean
+fn measure<F: Fn() -> String>(name: &str, f: F) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs < 5 || start.elapsed() < Duration::from_secs(1) {
+        assert!(!f().is_empty());
+        runs += 1;
+    }
+    let mean = start.elapsed() / runs;
+    println!("{:<24} {:>12?} ({} runs)", name, mean, runs);
+    mean
+}
+
+/// Generate `n` distinct IDs of `m` lowercase letters where only the last two differ in one index
+fn generate(n: usize, m: usize) -> Vec<String> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut ids: Vec<String> = (0..n - 1)
+        .map(|_| {
+            (0..m)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (b'a' + (state % 26) as u8) as char
+                }).collect()
+        }).collect();
+
+    let mut twin: Vec<char> = ids[n - 2].chars().collect();
+    twin[m / 2] = if twin[m / 2] == 'a' { 'b' } else { 'a' };
+    ids.push(twin.into_iter().collect());
+    ids
+}
+
+fn main() {
+    let input = read(include_str!("../src/y2018/input/2"));
+    println!("puzzle input ({} ids)", input.len());
+    measure("masked hash", || part2(&input));
+    measure("pairwise", || part2_pairwise(&input));
+
+    for &n in [1_000, 5_000].iter() {
+        let ids = generate(n, 26);
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        println!("generated input ({} ids)", n);
+        measure("masked hash", || part2(&ids));
+        measure("pairwise", || part2_pairwise(&ids));
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+//! Points on integer grids of any dimension
+
+use std::ops::{Add, Index, IndexMut, Neg, Sub};
+
+/// A point (or a move) on an `N` dimensional integer grid
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Point<const N: usize>(pub [i32; N]);
+
+impl<const N: usize> Default for Point<N> {
+    fn default() -> Point<N> {
+        Point([0; N])
+    }
+}
+
+impl<const N: usize> Point<N> {
+    /// The point with all coordinates zero
+    pub fn origin() -> Point<N> {
+        Point::default()
+    }
+
+    /// [Manhattan Distance](https://en.wikipedia.org/wiki/Taxicab_geometry) from the origin
+    pub fn manhattan_distance(self) -> i32 {
+        self.0.iter().map(|c| c.abs()).sum()
+    }
+
+    /// [Manhattan Distance](https://en.wikipedia.org/wiki/Taxicab_geometry) to another point
+    pub fn manhattan(self, other: Point<N>) -> i32 {
+        (self - other).manhattan_distance()
+    }
+
+    /// [Chebyshev Distance](https://en.wikipedia.org/wiki/Chebyshev_distance) to another point
+    pub fn chebyshev(self, other: Point<N>) -> i32 {
+        (self - other).0.iter().map(|c| c.abs()).max().unwrap_or(0)
+    }
+
+    /// The sign of each coordinate, i.e. a single step in the same direction
+    pub fn signum(self) -> Point<N> {
+        let mut p = self;
+        p.0.iter_mut().for_each(|c| *c = c.signum());
+        p
+    }
+
+    /// `O(N)` The `2N` points one step away along a single axis
+    pub fn orthogonal(self) -> Vec<Point<N>> {
+        let mut points = Vec::with_capacity(2 * N);
+        for axis in 0..N {
+            for step in [-1, 1].iter() {
+                let mut p = self;
+                p[axis] += step;
+                points.push(p);
+            }
+        }
+        points
+    }
+
+    /// `O(3^N)` The `3^N - 1` points that differ by at most one in every coordinate, including
+    /// diagonals
+    pub fn neighbours(self) -> Vec<Point<N>> {
+        let mut points = vec![self];
+        for axis in 0..N {
+            points = points
+                .into_iter()
+                .flat_map(|p| {
+                    [-1, 0, 1].iter().map(move |step| {
+                        let mut q = p;
+                        q[axis] += step;
+                        q
+                    })
+                }).collect();
+        }
+        points.retain(|p| *p != self);
+        points
+    }
+}
+
+impl<const N: usize> Index<usize> for Point<N> {
+    type Output = i32;
+
+    fn index(&self, axis: usize) -> &i32 {
+        &self.0[axis]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for Point<N> {
+    fn index_mut(&mut self, axis: usize) -> &mut i32 {
+        &mut self.0[axis]
+    }
+}
+
+impl<const N: usize> Add for Point<N> {
+    type Output = Point<N>;
+
+    fn add(self, rhs: Point<N>) -> Point<N> {
+        let mut p = self;
+        p.0.iter_mut().zip(rhs.0.iter()).for_each(|(a, b)| *a += b);
+        p
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Point<N>;
+
+    fn sub(self, rhs: Point<N>) -> Point<N> {
+        self + -rhs
+    }
+}
+
+impl<const N: usize> Neg for Point<N> {
+    type Output = Point<N>;
+
+    fn neg(self) -> Point<N> {
+        let mut p = self;
+        p.0.iter_mut().for_each(|c| *c = -*c);
+        p
+    }
+}
+
+impl Point<2> {
+    /// A point in 2 dimensions
+    pub const fn new(x: i32, y: i32) -> Point<2> {
+        Point([x, y])
+    }
+
+    /// The horizontal coordinate
+    pub fn x(self) -> i32 {
+        self.0[0]
+    }
+
+    /// The vertical coordinate
+    pub fn y(self) -> i32 {
+        self.0[1]
+    }
+}
+
+/// The smallest axis-aligned box holding a set of points (bounds included)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Bounds<const N: usize> {
+    pub min: Point<N>,
+    pub max: Point<N>,
+}
+
+impl<const N: usize> Bounds<N> {
+    /// `O(n)` The bounds of the given points, if there are any
+    pub fn of<I: IntoIterator<Item = Point<N>>>(points: I) -> Option<Bounds<N>> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Bounds {
+            min: first,
+            max: first,
+        };
+        points.for_each(|p| bounds.include(p));
+        Some(bounds)
+    }
+
+    /// Grow the bounds to hold the given point
+    pub fn include(&mut self, point: Point<N>) {
+        for axis in 0..N {
+            self.min[axis] = self.min[axis].min(point[axis]);
+            self.max[axis] = self.max[axis].max(point[axis]);
+        }
+    }
+
+    /// Check if the point is within the bounds
+    pub fn contains(&self, point: Point<N>) -> bool {
+        (0..N).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+    }
+
+    /// The number of grid points along each axis
+    pub fn size(&self) -> [u64; N] {
+        let mut size = [0; N];
+        for (axis, s) in size.iter_mut().enumerate() {
+            *s = (i64::from(self.max[axis]) - i64::from(self.min[axis]) + 1) as u64;
+        }
+        size
+    }
+
+    /// The number of grid points within the bounds
+    pub fn volume(&self) -> u64 {
+        self.size().iter().product()
+    }
+}
+
+#[test]
+fn distances() {
+    let a = Point([1, -2, 3]);
+    let b = Point([-2, 2, 3]);
+    assert_eq!(6, a.manhattan_distance());
+    assert_eq!(7, a.manhattan(b));
+    assert_eq!(4, a.chebyshev(b));
+    assert_eq!(Point([-3, 4, 0]), b - a);
+    assert_eq!(Point([-1, 1, 0]), (b - a).signum());
+    assert_eq!(0, Point::<0>::origin().chebyshev(Point([])));
+}
+
+#[test]
+fn neighbours() {
+    let p = Point::new(3, 4);
+    assert_eq!(
+        vec![
+            Point::new(2, 4),
+            Point::new(4, 4),
+            Point::new(3, 3),
+            Point::new(3, 5)
+        ],
+        p.orthogonal()
+    );
+    assert_eq!(8, p.neighbours().len());
+    assert!(p.neighbours().iter().all(|q| p.chebyshev(*q) == 1));
+    assert_eq!(26, Point([0; 3]).neighbours().len());
+    assert_eq!(80, Point([0; 4]).neighbours().len());
+    assert_eq!(8, Point([0; 4]).orthogonal().len());
+}
+
+#[test]
+fn bounds() {
+    assert_eq!(None, Bounds::<2>::of(vec![]));
+
+    let points = vec![Point([1, 5, -1]), Point([-2, 3, 0]), Point([0, 4, 2])];
+    let bounds = Bounds::of(points.clone()).unwrap();
+    assert_eq!(Point([-2, 3, -1]), bounds.min);
+    assert_eq!(Point([1, 5, 2]), bounds.max);
+    assert!(points.iter().all(|p| bounds.contains(*p)));
+    assert!(!bounds.contains(Point([2, 4, 0])));
+    assert_eq!([4, 3, 4], bounds.size());
+    assert_eq!(48, bounds.volume());
+}
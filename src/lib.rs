@@ -16,6 +16,10 @@
 //!
 //! [Advent of Code]: https://adventofcode.com/
 
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+
+pub mod geometry;
 pub mod y2017;
 pub mod y2018;
 
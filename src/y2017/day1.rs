@@ -3,6 +3,11 @@
 
 //! Inverse Captcha ([Statement](https://adventofcode.com/2017/day/1)).
 
+use std::cmp::min;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::super::TRUST;
+
 /// Review a sequence of digits (your puzzle input) and find the sum of all digits that match the
 /// next digit in the list. The list is circular, so the digit after the last digit is the first
 /// digit in the list. `O(n)` with no additional space.
@@ -13,17 +18,166 @@ pub fn part1(input: &[u8]) -> u32 {
 /// Same as Part 1, but instead of considering the next digit, it wants you to consider the digit
 /// halfway around the circular list. `O(n)` with no additional space.
 pub fn part2(input: &[u8]) -> u32 {
-    debug_assert!(input.len() % 2 == 0);
+    debug_assert!(input.len().is_multiple_of(2));
     skip_take_sum(input, input.len() / 2)
 }
 
 /// Read digits from the input.
 pub fn read_digits(input: &str) -> Vec<u8> {
-    input
-        .chars()
-        .filter(|c| char::is_digit(*c, 10))
-        .map(|c| char::to_digit(c, 10).unwrap() as u8)
-        .collect()
+    read_radix(input, 10).expect(TRUST)
+}
+
+/// Read digits in the given radix from the input, skipping any other characters. `None` if the
+/// radix is not between 2 and 36.
+pub fn read_radix(input: &str, radix: u32) -> Option<Vec<u8>> {
+    if !(2..=36).contains(&radix) {
+        return None;
+    }
+
+    Some(
+        input
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .map(|d| d as u8)
+            .collect(),
+    )
+}
+
+/// Bytes per word in the chunked comparison
+const WORD: usize = 8;
+
+/// The high bit in every lane of a word
+const HIGH: u64 = 0x8080_8080_8080_8080;
+
+/// Same as [skip_take_sum], a word of 8 digits at a time. `O(n)` with no additional space. Like the
+/// iterator version, a skip of at least the length of the input compares each digit with itself.
+/// The sum is a `u64` (like [stream_sum]) so it does not overflow on very large inputs.
+pub fn chunked_sum(digits: &[u8], skip: usize) -> u64 {
+    let n = digits.len();
+    let skip = if skip >= n { 0 } else { skip };
+    let (front, _) = word_sum(&digits[..n - skip], &digits[skip..], 0);
+    let (back, _) = word_sum(&digits[n - skip..], &digits[..skip], 0);
+    front + back
+}
+
+/// Sum the `zero`-based values of the bytes in `a` that equal the byte at the same position in `b`,
+/// and count them. Compares a word at a time with the usual bit tricks for finding zero bytes, so it
+/// needs no special hardware.
+fn word_sum(a: &[u8], b: &[u8], zero: u8) -> (u64, u64) {
+    let (mut sum, mut count) = (0, 0);
+    for (x, y) in a.chunks_exact(WORD).zip(b.chunks_exact(WORD)) {
+        let (u, v) = (load(x), load(y));
+        let diff = u ^ v;
+        // The high bit of each lane is set if and only if that lane of `diff` is zero
+        let same = !(((diff & !HIGH) + !HIGH) | diff | !HIGH);
+        sum += lanes(u & ((same >> 7) * 0xff));
+        count += u64::from(same.count_ones());
+    }
+
+    // Left over bytes
+    let whole = a.len() - a.len() % WORD;
+    for (x, y) in a[whole..].iter().zip(b[whole..].iter()) {
+        if x == y {
+            sum += u64::from(*x);
+            count += 1;
+        }
+    }
+    (sum - count * u64::from(zero), count)
+}
+
+/// Read a word from 8 bytes
+fn load(bytes: &[u8]) -> u64 {
+    let mut word = [0; WORD];
+    word.copy_from_slice(bytes);
+    u64::from_le_bytes(word)
+}
+
+/// Add up the 8 byte lanes of a word, pairwise into 16 bit lanes and then with a multiply
+fn lanes(word: u64) -> u64 {
+    let pairs = (word & 0x00ff_00ff_00ff_00ff) + ((word >> 8) & 0x00ff_00ff_00ff_00ff);
+    pairs.wrapping_mul(0x0001_0001_0001_0001) >> 48
+}
+
+/// Same as [chunked_sum] for ASCII digits read from a seekable source, e.g. a large file, without
+/// holding it in memory. The source must hold only digits, optionally followed by whitespace.
+/// `O(n)` with `O(c)` space for two chunks of `c` bytes.
+pub fn stream_sum<R: Read + Seek>(source: &mut R, skip: usize) -> io::Result<u64> {
+    const CHUNK: usize = 1 << 16;
+
+    // Find the number of digits by skipping trailing whitespace
+    let mut n = source.seek(SeekFrom::End(0))? as usize;
+    let mut last = [0];
+    while n > 0 {
+        source.seek(SeekFrom::Start(n as u64 - 1))?;
+        source.read_exact(&mut last)?;
+        if !last[0].is_ascii_whitespace() {
+            break;
+        }
+        n -= 1;
+    }
+    let skip = if skip >= n { 0 } else { skip };
+
+    // Walk two cursors through the digits, the second `skip` digits ahead and wrapping around
+    let (mut a, mut b) = (vec![0; CHUNK], vec![0; CHUNK]);
+    let (mut sum, mut position) = (0, 0);
+    while position < n {
+        let size = min(CHUNK, n - position);
+        source.seek(SeekFrom::Start(position as u64))?;
+        source.read_exact(&mut a[..size])?;
+
+        // The second cursor may wrap around within this chunk
+        let ahead = (position + skip) % n;
+        let before = min(size, n - ahead);
+        source.seek(SeekFrom::Start(ahead as u64))?;
+        source.read_exact(&mut b[..before])?;
+        source.seek(SeekFrom::Start(0))?;
+        source.read_exact(&mut b[before..size])?;
+
+        if let Some(c) = a[..size].iter().find(|c| !c.is_ascii_digit()) {
+            let error = format!("unexpected byte {:?} in digits", *c as char);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+
+        let (part, _) = word_sum(&a[..size], &b[..size], b'0');
+        sum += part;
+        position += size;
+    }
+
+    Ok(sum)
+}
+
+/// How the end of a sequence is treated when looking ahead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// The element after the last is the first
+    Circular,
+    /// Nothing follows the last element
+    Linear,
+}
+
+/// `O(n*k)` for `k` offsets with no additional space. For each offset, find the elements that
+/// match the element that many positions ahead and add up their weights. Offsets beyond the end
+/// wrap around in a circular sequence and match nothing in a linear one.
+pub fn match_sum<T, I, W>(items: &[T], offsets: I, shape: Shape, weight: W) -> u64
+where
+    T: Eq,
+    I: IntoIterator<Item = usize>,
+    W: Fn(&T) -> u64,
+{
+    let n = items.len();
+    let mut sum = 0;
+    for offset in offsets {
+        let pairs = match shape {
+            Shape::Circular if n > 0 => n,
+            Shape::Circular => 0,
+            Shape::Linear => n.saturating_sub(offset),
+        };
+        sum += (0..pairs)
+            .filter(|&i| items[i] == items[(i + offset) % n])
+            .map(|i| weight(&items[i]))
+            .sum::<u64>();
+    }
+    sum
 }
 
 /// Use iterator `skip` and `take` to setup a pair of shifted iterators over the input to zip
@@ -58,6 +212,59 @@ fn examples() {
     assert_eq!(part2(&read_digits("12131415")), 4);
 }
 
+#[test]
+fn generic() {
+    let digits = read_digits("91212129");
+    let value = |d: &u8| u64::from(*d);
+    assert_eq!(9, match_sum(&digits, Some(1), Shape::Circular, value));
+    assert_eq!(0, match_sum(&digits, Some(1), Shape::Linear, value));
+    assert_eq!(
+        u64::from(skip_take_sum(&digits, 2) + skip_take_sum(&digits, 3)),
+        match_sum(&digits, 2..4, Shape::Circular, value)
+    );
+    assert_eq!(6, match_sum(&digits, Some(10), Shape::Circular, value));
+    assert_eq!(0, match_sum(&digits, Some(10), Shape::Linear, value));
+
+    let words = ["a", "b", "a", "b", "c"];
+    assert_eq!(2, match_sum(&words, Some(2), Shape::Linear, |_| 1));
+    assert_eq!(2, match_sum(&words, 1..3, Shape::Circular, |_| 1));
+    assert_eq!(0, match_sum(&[] as &[u8], 1..10, Shape::Circular, value));
+
+    assert_eq!(Some(vec![15, 0, 10]), read_radix("f0a", 16));
+    assert_eq!(Some(vec![1, 0]), read_radix("1 02", 2));
+    assert_eq!(None, read_radix("1", 37));
+}
+
+#[test]
+fn chunked() {
+    use std::io::Cursor;
+
+    let digits = read_digits(include_str!("input/1"));
+    for skip in (0..20).chain(digits.len() / 2 - 3..digits.len() + 3) {
+        assert_eq!(
+            u64::from(skip_take_sum(&digits, skip)),
+            chunked_sum(&digits, skip)
+        );
+    }
+
+    // The stream holds the text, reading it in chunks smaller than the input
+    let text: Vec<u8> = include_str!("input/1").trim().repeat(40).into_bytes();
+    let digits = read_digits(std::str::from_utf8(&text).unwrap());
+    let mut padded = text.clone();
+    padded.extend_from_slice(b"\n\n");
+    for &skip in [0, 1, 7, 8, 9, 65_536, digits.len() / 2, digits.len() - 1].iter() {
+        let expected = u64::from(skip_take_sum(&digits, skip));
+        assert_eq!(expected, stream_sum(&mut Cursor::new(&text), skip).unwrap());
+        assert_eq!(
+            expected,
+            stream_sum(&mut Cursor::new(&padded), skip).unwrap()
+        );
+    }
+
+    assert_eq!(0, stream_sum(&mut Cursor::new(b""), 1).unwrap());
+    assert!(stream_sum(&mut Cursor::new(b"12x4"), 1).is_err());
+}
+
 #[test]
 fn solution() {
     let digits = read_digits(include_str!("input/1"));
@@ -3,16 +3,166 @@
 
 //! Corruption Checksum ([Statement](https://adventofcode.com/2017/day/2))
 
+use std::iter::Sum;
+use std::str::FromStr;
+
 /// Calculate the spreadsheet's checksum. For each row, determine the difference between the largest
 /// value and the smallest value; the checksum is the sum of all of these differences.
-pub fn part1(matrix: &[Vec<u32>]) -> u32 {
-    matrix.iter().map(Vec::as_slice).map(range).sum()
+pub fn part1(sheet: &Sheet<u32>) -> u32 {
+    checksum(sheet, &Range, Axis::Rows)
 }
 
 /// Find the only two numbers in each row where one evenly divides the other, divide them, and add
 /// up each line's result
-pub fn part2(matrix: &mut [Vec<u32>]) -> u32 {
-    matrix.iter_mut().map(multiples).sum()
+pub fn part2(sheet: &Sheet<u32>) -> u32 {
+    checksum(sheet, &Quotient, Axis::Rows)
+}
+
+/// Reduce a row (or column) of the sheet to a single value
+pub trait RowReducer {
+    /// The reduced value
+    type Output;
+
+    /// Reduce the values of a row
+    fn reduce(&self, row: &[u32]) -> Self::Output;
+}
+
+/// Any function of a row is a reducer
+impl<O, F: Fn(&[u32]) -> O> RowReducer for F {
+    type Output = O;
+
+    fn reduce(&self, row: &[u32]) -> O {
+        self(row)
+    }
+}
+
+/// The difference between the largest and smallest values (`0` for an empty row)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Range;
+
+impl RowReducer for Range {
+    type Output = u32;
+
+    fn reduce(&self, row: &[u32]) -> u32 {
+        if row.is_empty() {
+            0
+        } else {
+            range(row)
+        }
+    }
+}
+
+/// The quotient of the only two values where one evenly divides the other (see [multiples]), or
+/// `0` if there are none
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quotient;
+
+impl RowReducer for Quotient {
+    type Output = u32;
+
+    fn reduce(&self, row: &[u32]) -> u32 {
+        multiples(row).unwrap_or(0)
+    }
+}
+
+/// The sum of the values
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Total;
+
+impl RowReducer for Total {
+    type Output = u64;
+
+    fn reduce(&self, row: &[u32]) -> u64 {
+        row.iter().map(|&v| u64::from(v)).sum()
+    }
+}
+
+/// The mean of the values (`0` for an empty row)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mean;
+
+impl RowReducer for Mean {
+    type Output = f64;
+
+    fn reduce(&self, row: &[u32]) -> f64 {
+        if row.is_empty() {
+            0.0
+        } else {
+            Total.reduce(row) as f64 / row.len() as f64
+        }
+    }
+}
+
+/// The greatest common divisor of the values (`0` for an empty row)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gcd;
+
+impl RowReducer for Gcd {
+    type Output = u32;
+
+    fn reduce(&self, row: &[u32]) -> u32 {
+        row.iter().fold(0, |a, &b| gcd(a, b))
+    }
+}
+
+/// The number of pairs of non-zero values where one evenly divides the other (see
+/// [divisible_pairs])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DivisiblePairs;
+
+impl RowReducer for DivisiblePairs {
+    type Output = u64;
+
+    fn reduce(&self, row: &[u32]) -> u64 {
+        divisible_pairs(row).len() as u64
+    }
+}
+
+/// The largest ratio between two non-zero values, i.e. the largest over the smallest (`0` if the row
+/// has no non-zero values)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxRatio;
+
+impl RowReducer for MaxRatio {
+    type Output = f64;
+
+    fn reduce(&self, row: &[u32]) -> f64 {
+        let values = row.iter().filter(|&&v| v > 0);
+        match (values.clone().min(), values.max()) {
+            (Some(&min), Some(&max)) => f64::from(max) / f64::from(min),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Which way to reduce the sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Reduce each row
+    Rows,
+    /// Reduce each column
+    Columns,
+}
+
+/// Reduce each row (or column) of the sheet with the given reducer and add up the results
+pub fn checksum<R>(sheet: &Sheet<u32>, reducer: &R, axis: Axis) -> R::Output
+where
+    R: RowReducer + ?Sized,
+    R::Output: Sum,
+{
+    match axis {
+        Axis::Rows => sheet.rows().map(|row| reducer.reduce(&row)).sum(),
+        Axis::Columns => sheet.columns().map(|c| reducer.reduce(&c)).sum(),
+    }
+}
+
+/// Greatest common divisor by Euclid's algorithm
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 
 /// Range of values in the row (i.e. difference between the max and the min values) `O(n)`.
@@ -22,34 +172,200 @@ pub fn range(row: &[u32]) -> u32 {
     max - min
 }
 
+/// The ways cells are delimited in a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Tab separated; empty cells are blank
+    Tsv,
+    /// Comma separated; empty cells are blank
+    Csv,
+    /// Separated by any run of whitespace; no cell is ever blank
+    Whitespace,
+}
+
+/// A single cell of the sheet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell<T> {
+    /// A cell with a value
+    Value(T),
+    /// An empty cell
+    Blank,
+    /// A cell whose text is not a value
+    Invalid(String),
+}
+
+/// A spreadsheet of values, which keeps track of blank and invalid cells. Rows may have different
+/// lengths and blank lines in the input are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet<T> {
+    cells: Vec<Vec<Cell<T>>>,
+    lines: Vec<usize>,
+}
+
+impl<T: FromStr> Sheet<T> {
+    /// `O(n)` Read a sheet in the given format
+    pub fn read(input: &str, format: Format) -> Sheet<T> {
+        let cell = |text: &str| {
+            let text = text.trim();
+            if text.is_empty() {
+                Cell::Blank
+            } else {
+                T::from_str(text)
+                    .map(Cell::Value)
+                    .unwrap_or_else(|_| Cell::Invalid(text.to_string()))
+            }
+        };
+
+        let (lines, cells) = input
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                let row = match format {
+                    Format::Tsv => l.split('\t').map(cell).collect(),
+                    Format::Csv => l.split(',').map(cell).collect(),
+                    Format::Whitespace => l.split_whitespace().map(cell).collect(),
+                };
+                (i + 1, row)
+            }).unzip();
+
+        Sheet { cells, lines }
+    }
+}
+
+impl<T> Sheet<T> {
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of columns in the longest row
+    pub fn width(&self) -> usize {
+        self.cells.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// The cell at the given row and column, if the sheet has one there
+    pub fn get(&self, row: usize, column: usize) -> Option<&Cell<T>> {
+        self.cells.get(row).and_then(|r| r.get(column))
+    }
+
+    /// The line of the input the row was read from, counting from 1
+    pub fn line(&self, row: usize) -> Option<usize> {
+        self.lines.get(row).cloned()
+    }
+
+    /// The blank and invalid cells along with their (row, column) positions, as taken by `get`.
+    /// Use `line` to find the input line of the row.
+    pub fn issues(&self) -> Vec<(usize, usize, &Cell<T>)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, c)| (i, j, c)))
+            .filter(|(_, _, c)| value(c).is_none())
+            .collect()
+    }
+
+    /// Iterate over the values in a row
+    pub fn row(&self, row: usize) -> impl Iterator<Item = &T> {
+        self.cells
+            .get(row)
+            .into_iter()
+            .flat_map(|r| r.iter())
+            .filter_map(value)
+    }
+
+    /// Iterate over the values in a column, skipping rows too short to have it
+    pub fn column(&self, column: usize) -> impl Iterator<Item = &T> {
+        self.cells
+            .iter()
+            .filter_map(move |r| r.get(column))
+            .filter_map(value)
+    }
+}
+
+impl<T: Clone> Sheet<T> {
+    /// Iterate over the values of each row
+    pub fn rows(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        (0..self.height()).map(move |i| self.row(i).cloned().collect())
+    }
+
+    /// Iterate over the values of each column
+    pub fn columns(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        (0..self.width()).map(move |j| self.column(j).cloned().collect())
+    }
+}
+
+/// The value in a cell, if any
+fn value<T>(cell: &Cell<T>) -> Option<&T> {
+    match cell {
+        Cell::Value(v) => Some(v),
+        _ => None,
+    }
+}
+
 /// Read a 2-dimensional array of numbers delimited by newlines and whitespace
-pub fn read_matrix(input: &str) -> Vec<Vec<u32>> {
-    use std::str::FromStr;
-    input
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .map(|l| {
-            l.split_whitespace()
-                .filter_map(|s| u32::from_str(s).ok())
-                .collect()
-        }).collect()
+pub fn read_matrix(input: &str) -> Sheet<u32> {
+    Sheet::read(input, Format::Whitespace)
 }
 
 /// Each row has 2 numbers where the larger is an whole multiple of the smaller. Find those
-/// and return the quotient
-pub fn multiples(row: &mut Vec<u32>) -> u32 {
-    // Sort the numbers to go from low to high
-    row.sort();
-    for i in 0..row.len() {
-        for j in i + 1..row.len() {
-            if row[j] % row[i] == 0 {
-                return row[j] / row[i];
+/// and return the quotient, or `None` if there are no such numbers. Zeros are ignored and equal
+/// numbers divide each other.
+pub fn multiples(row: &[u32]) -> Option<u32> {
+    divisible_pairs(row)
+        .first()
+        .map(|&(divisor, multiple)| row[multiple] / row[divisor])
+}
+
+/// Rows with values up to this are searched by sieving multiples rather than by comparing pairs
+pub const SIEVE_LIMIT: u32 = 1 << 16;
+
+/// Find all pairs of positions `(i, j)` in the row where `row[i]` evenly divides `row[j]`, in order.
+/// Zeros are ignored and equal values are paired once, with `i < j`.
+///
+/// `O(v log v + p)` for rows with values up to `v <= SIEVE_LIMIT` and `p` pairs: each distinct value
+/// steps through its multiples up to the largest value, like a sieve. `O(n^2)` by comparing pairs
+/// otherwise, or when that is cheaper (e.g. short rows).
+pub fn divisible_pairs(row: &[u32]) -> Vec<(usize, usize)> {
+    let largest = row.iter().cloned().max().unwrap_or(0);
+    let sieve = u64::from(largest) * u64::from(32 - largest.leading_zeros());
+    let compare = (row.len() as u64).pow(2);
+    let mut pairs = Vec::new();
+
+    if largest <= SIEVE_LIMIT && sieve < compare {
+        // Positions of each value
+        let mut positions = vec![Vec::new(); largest as usize + 1];
+        for (i, &v) in row.iter().enumerate().filter(|(_, &v)| v > 0) {
+            positions[v as usize].push(i);
+        }
+
+        for divisor in 1..=largest as usize {
+            let here = &positions[divisor];
+            if here.is_empty() {
+                continue;
+            }
+            for (k, &i) in here.iter().enumerate() {
+                pairs.extend(here[k + 1..].iter().map(|&j| (i, j)));
+            }
+            for multiple in (2 * divisor..=largest as usize).step_by(divisor) {
+                for &i in here.iter() {
+                    pairs.extend(positions[multiple].iter().map(|&j| (i, j)));
+                }
+            }
+        }
+    } else {
+        for (i, &a) in row.iter().enumerate().filter(|(_, &a)| a > 0) {
+            for (j, &b) in row.iter().enumerate() {
+                let once = a != b || i < j;
+                if i != j && b > 0 && once && b.is_multiple_of(a) {
+                    pairs.push((i, j));
+                }
             }
         }
     }
 
-    // We're told the input always has a pair we're looking for
-    unreachable!()
+    pairs.sort_unstable();
+    pairs
 }
 
 #[test]
@@ -65,12 +381,104 @@ fn examples() {
     3 8 6 5
     ";
     assert_eq!(part1(&read_matrix(one)), 18);
-    assert_eq!(part2(&mut read_matrix(two)), 9);
+    assert_eq!(part2(&read_matrix(two)), 9);
+}
+
+#[test]
+fn sheets() {
+    let csv: Sheet<u32> = Sheet::read("1,2,x\n\n4,,6\n7", Format::Csv);
+    assert_eq!((3, 3), (csv.height(), csv.width()));
+    assert_eq!(
+        vec![
+            (0, 2, &Cell::Invalid(String::from("x"))),
+            (1, 1, &Cell::Blank)
+        ],
+        csv.issues()
+    );
+    assert_eq!(
+        vec![Some(1), Some(3), Some(4), None],
+        (0..4).map(|r| csv.line(r)).collect::<Vec<_>>()
+    );
+    for (row, column, cell) in csv.issues() {
+        assert_eq!(Some(cell), csv.get(row, column));
+    }
+    assert_eq!(Some(3), csv.line(csv.issues()[1].0));
+    assert_eq!(
+        vec![vec![1, 2], vec![4, 6], vec![7]],
+        csv.rows().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![vec![1, 4, 7], vec![2], vec![6]],
+        csv.columns().collect::<Vec<_>>()
+    );
+    assert_eq!(Some(&Cell::Value(6)), csv.get(1, 2));
+    assert_eq!(None, csv.get(2, 1));
+
+    let tsv: Sheet<u32> = Sheet::read("5\t1\t9\t5\n7\t\t3", Format::Tsv);
+    assert_eq!(vec![(1, 1, &Cell::Blank)], tsv.issues());
+    assert_eq!(12, part1(&tsv));
+
+    let spaced: Sheet<i64> = Sheet::read("  -1   2.5  3 ", Format::Whitespace);
+    assert_eq!(vec![-1, 3], spaced.row(0).cloned().collect::<Vec<_>>());
+    assert_eq!(1, spaced.issues().len());
+}
+
+#[test]
+fn reducers() {
+    let sheet = read_matrix(
+        r"
+    5 1 9 5
+    7 5 3
+    2 4 6 8
+    ",
+    );
+    assert_eq!(18, checksum(&sheet, &Range, Axis::Rows));
+    assert_eq!(5 + 4 + 6 + 3, checksum(&sheet, &Range, Axis::Columns));
+    assert_eq!(55, checksum(&sheet, &Total, Axis::Rows));
+    assert_eq!(55, checksum(&sheet, &Total, Axis::Columns));
+    assert_eq!(5.0 + 5.0 + 5.0, checksum(&sheet, &Mean, Axis::Rows));
+    assert_eq!(1 + 1 + 2, checksum(&sheet, &Gcd, Axis::Rows));
+    assert_eq!(8, checksum(&sheet, &DivisiblePairs, Axis::Rows));
+    assert_eq!(
+        9.0 + 7.0 / 3.0 + 4.0,
+        checksum(&sheet, &MaxRatio, Axis::Rows)
+    );
+
+    let count = |row: &[u32]| row.len();
+    assert_eq!(11, checksum(&sheet, &count, Axis::Columns));
+
+    assert_eq!(0, Range.reduce(&[]));
+    assert_eq!(0.0, Mean.reduce(&[]));
+    assert_eq!(0.0, MaxRatio.reduce(&[0, 0]));
+    assert_eq!(1, DivisiblePairs.reduce(&[0, 3, 3, 0]));
+}
+
+#[test]
+fn divisors() {
+    let row = [5, 9, 2, 8];
+    assert_eq!(Some(4), multiples(&row));
+    assert_eq!([5, 9, 2, 8], row);
+    assert_eq!(None, multiples(&[7, 5, 3]));
+    assert_eq!(None, multiples(&[]));
+    assert_eq!(Some(3), multiples(&[0, 9, 0, 3]));
+    assert_eq!(Some(1), multiples(&[4, 7, 4]));
+
+    let row = [6, 0, 3, 3, 12, 5];
+    assert_eq!(
+        vec![(0, 4), (2, 0), (2, 3), (2, 4), (3, 0), (3, 4)],
+        divisible_pairs(&row)
+    );
+
+    // The sieve for long rows agrees with the pairwise search for large values
+    let long = row.repeat(10);
+    let big: Vec<u32> = long.iter().map(|&v| v * (SIEVE_LIMIT + 1)).collect();
+    assert_eq!(825, divisible_pairs(&long).len());
+    assert_eq!(divisible_pairs(&long), divisible_pairs(&big));
 }
 
 #[test]
 fn solution() {
-    let mut digits = read_matrix(include_str!("input/2"));
+    let digits = read_matrix(include_str!("input/2"));
     assert_eq!(part1(&digits), 32020);
-    assert_eq!(part2(&mut digits), 236);
+    assert_eq!(part2(&digits), 236);
 }
@@ -3,25 +3,24 @@
 
 //! Spiral Memory ([Statement](https://adventofcode.com/2017/day/3))
 
-use std::collections::HashMap;
+use geometry::Point;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::ops::Add;
+
+use super::super::TRUST;
 
 /// `O(n)` with no storage. Each square on the grid is allocated in a spiral pattern starting at a
 /// location marked 1 and then counting up while spiraling outward. How many steps are required to
 /// carry the data from the square identified in your puzzle input all the way to the access port?
 pub fn part1(input: usize) -> i32 {
-    spiral()
-        .take(input - 1)
-        .fold(Move::default(), |a, x| a + x)
-        .manhattan_distance()
+    position(input).expect(TRUST).manhattan_distance()
 }
 
 /// In the same allocation order as shown above, they store the sum of the values in all adjacent
 /// squares, including diagonals.
 pub fn part2(input: usize) -> usize {
     // The set of moves to reach 8 potential neighbors
-    let neighbors = vec![R, L, U, D, R + U, R + D, L + D, L + U];
+    let neighbors = Move::origin().neighbours();
 
     // Keep a record of all the slots we've filled so far
     let mut position = Move::default();
@@ -53,47 +52,126 @@ pub fn part2(input: usize) -> usize {
     unreachable!()
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
-/// A move in 2 dimensions
-pub struct Move {
-    pub x: i32,
-    pub y: i32,
+/// `O(n)` The position of the square with the given index, relative to square 1. Squares are
+/// numbered from 1, so there is no square 0.
+pub fn position(index: usize) -> Option<Move> {
+    let moves = index.checked_sub(1)?;
+    Some(spiral().take(moves).fold(Move::default(), |a, x| a + x))
 }
 
-/// Move right
-pub const R: Move = Move { x: 1, y: 0 };
+/// A path on the grid as a list of moves, each as long as possible
+#[derive(Clone, Debug, Eq, PartialEq, Default)]
+pub struct Route {
+    /// The moves in order, none of them empty
+    pub moves: Vec<Move>,
+}
 
-/// Move left
-pub const L: Move = Move { x: -1, y: 0 };
+impl Route {
+    /// The number of single steps along the route
+    pub fn steps(&self) -> i32 {
+        self.moves.iter().map(|m| m.manhattan_distance()).sum()
+    }
 
-/// Move up
-pub const U: Move = Move { x: 0, y: 1 };
+    /// The squares visited along the route, starting at `from`
+    pub fn squares(&self, from: Move) -> Vec<Move> {
+        let mut squares = vec![from];
+        let mut at = from;
+        for m in self.moves.iter() {
+            let step = m.signum();
+            for _ in 0..m.manhattan_distance() {
+                at = at + step;
+                squares.push(at);
+            }
+        }
+        squares
+    }
+}
 
-/// Move down
-pub const D: Move = Move { x: 0, y: -1 };
+/// Pretty print routes as compressed moves (e.g. `3L2D`)
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for m in self.moves.iter() {
+            write!(f, "{}", m)?;
+        }
+        Ok(())
+    }
+}
 
-/// The fixed sequence of moves we make in a Spiral
-const SEQUENCE: [Move; 4] = [D, R, U, L];
+/// `O(n)` A shortest route from the square with the given index back to square 1, moving
+/// horizontally first and then vertically. `None` for index 0, like [position].
+pub fn route(index: usize) -> Option<Route> {
+    let at = position(index)?;
+    let moves = [Move::new(-at.x(), 0), Move::new(0, -at.y())];
+    Some(Route {
+        moves: moves
+            .iter()
+            .cloned()
+            .filter(|m| *m != Move::default())
+            .collect(),
+    })
+}
 
-impl Add for Move {
-    type Output = Move;
+/// `O(r^2)` Render the spiral's numbers in a square grid around square 1, just large enough to
+/// hold the square with the given index, with the squares on its route to square 1 in brackets.
+/// `None` for index 0, like [position].
+pub fn render(index: usize) -> Option<String> {
+    let start = position(index)?;
+    let on_route: HashSet<Move> = route(index)?.squares(start).into_iter().collect();
 
-    /// Add two moves to get aggregate move
-    fn add(self, rhs: Move) -> Move {
-        Move {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+    // Number the squares of the grid
+    let radius = start.chebyshev(Move::origin());
+    let side = (2 * radius + 1) as usize;
+    let mut grid = HashMap::new();
+    let mut at = Move::default();
+    grid.insert(at, 1);
+    for (n, m) in spiral().take(side * side - 1).enumerate() {
+        at = at + m;
+        grid.insert(at, n + 2);
     }
-}
 
-impl Move {
-    /// [Manhattan Distance](https://en.wikipedia.org/wiki/Taxicab_geometry)
-    pub fn manhattan_distance(self) -> i32 {
-        self.x.abs() + self.y.abs()
+    let width = (side * side).to_string().len();
+    let mut out = String::new();
+    for y in (-radius..=radius).rev() {
+        let mut row = String::new();
+        for x in -radius..=radius {
+            let square = Move::new(x, y);
+            let (open, close) = if on_route.contains(&square) {
+                ('[', ']')
+            } else {
+                (' ', ' ')
+            };
+            row.push_str(&format!(
+                "{}{:>w$}{}",
+                open,
+                grid[&square],
+                close,
+                w = width
+            ));
+        }
+        out.push_str(row.trim_end());
+        out.push('\n');
     }
+    Some(out)
 }
 
+/// A move in 2 dimensions
+pub type Move = Point<2>;
+
+/// Move right
+pub const R: Move = Move::new(1, 0);
+
+/// Move left
+pub const L: Move = Move::new(-1, 0);
+
+/// Move up
+pub const U: Move = Move::new(0, 1);
+
+/// Move down
+pub const D: Move = Move::new(0, -1);
+
+/// The fixed sequence of moves we make in a Spiral
+const SEQUENCE: [Move; 4] = [D, R, U, L];
+
 /// State for spiral iterator
 #[derive(Debug, Default)]
 struct Spiral {
@@ -144,18 +222,19 @@ pub fn spiral() -> impl Iterator<Item = Move> {
 /// Pretty print moves
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.x.abs() != 0 {
-            if self.x.abs() != 1 {
-                write!(f, "{}", self.x.abs())?;
+        let (x, y) = (self.x(), self.y());
+        if x.abs() != 0 {
+            if x.abs() != 1 {
+                write!(f, "{}", x.abs())?;
             }
-            write!(f, "{}", if self.x > 0 { "R" } else { "L" })?;
+            write!(f, "{}", if x > 0 { "R" } else { "L" })?;
         }
 
-        if self.y.abs() != 0 {
-            if self.y.abs() != 1 {
-                write!(f, "{}", self.y.abs())?;
+        if y.abs() != 0 {
+            if y.abs() != 1 {
+                write!(f, "{}", y.abs())?;
             }
-            write!(f, "{}", if self.y > 0 { "U" } else { "D" })?;
+            write!(f, "{}", if y > 0 { "U" } else { "D" })?;
         }
 
         Ok(())
@@ -170,6 +249,32 @@ fn examples() {
     assert_eq!(part1(1024), 31);
 }
 
+#[test]
+fn routes() {
+    assert_eq!("3L2D", Move::new(-3, -2).to_string());
+    assert_eq!("2R", (R + R).to_string());
+    assert_eq!("", route(1).unwrap().to_string());
+    assert_eq!("2LD", route(12).unwrap().to_string());
+    assert_eq!("2U", route(23).unwrap().to_string());
+    assert_eq!("15R16D", route(1024).unwrap().to_string());
+    assert_eq!(31, route(1024).unwrap().steps());
+    assert_eq!(
+        render(12).unwrap(),
+        [
+            " 17  16  15  14  13",
+            " 18   5 [ 4][ 3][12]",
+            " 19   6 [ 1]  2  11",
+            " 20   7   8   9  10",
+            " 21  22  23  24  25",
+            "",
+        ].join("\n")
+    );
+    assert_eq!(Some(String::from("[1]\n")), render(1));
+    assert_eq!(None, position(0));
+    assert_eq!(None, route(0));
+    assert_eq!(None, render(0));
+}
+
 #[test]
 fn solution() {
     assert_eq!(part1(265149), 438);
@@ -3,45 +3,349 @@
 
 //! High-Entropy Passphrases ([Statement](https://adventofcode.com/2017/day/4))
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 /// `O(n)` with `O(m)` space where `m` is the number of distinct words in a line.
 pub fn part1(input: &str) -> usize {
-    input.lines().filter(no_repeat).count()
+    input.lines().filter(|l| no_repeat(l)).count()
 }
 
 /// `O(n)` with `O(m)` space where `m` is the number of distinct words in a line.
 pub fn part2(input: &str) -> usize {
-    input.lines().filter(no_anagram).count()
+    input.lines().filter(|l| no_anagram(l)).count()
 }
 
 /// Check if the line has any repeated words
-pub fn no_repeat(line: &&str) -> bool {
+pub fn no_repeat(line: &str) -> bool {
     let mut seen = HashSet::new();
     line.split_whitespace().all(|w| seen.insert(w))
 }
 
 /// Check if the line has words that are anagrams of each other
-pub fn no_anagram(line: &&str) -> bool {
+pub fn no_anagram(line: &str) -> bool {
     let mut seen = HashSet::new();
     line.split_whitespace()
-        .map(|m| {
-            let mut vec: Vec<char> = m.chars().collect();
-            vec.sort();
-            vec
-        }).all(|w| seen.insert(w))
+        .map(anagram_key)
+        .all(|w| seen.insert(w))
+}
+
+/// Words are anagrams of each other if they have the same sorted characters
+fn anagram_key(word: &str) -> Vec<char> {
+    let mut key: Vec<char> = word.chars().collect();
+    key.sort();
+    key
+}
+
+/// A single rule a passphrase must follow
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    /// No word appears more than once
+    NoRepeats,
+    /// No two words are anagrams of each other
+    NoAnagrams,
+    /// At least this many words
+    MinWords(usize),
+    /// Every word is in the dictionary
+    Dictionary(HashSet<String>),
+    /// At least this many bits of entropy, estimated as the number of characters times the Shannon
+    /// entropy of the characters in the passphrase
+    MinEntropy(f64),
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::NoRepeats => write!(f, "no repeated words"),
+            Rule::NoAnagrams => write!(f, "no anagrams"),
+            Rule::MinWords(n) => write!(f, "at least {} words", n),
+            Rule::Dictionary(_) => write!(f, "dictionary words only"),
+            Rule::MinEntropy(bits) => write!(f, "at least {} bits of entropy", bits),
+        }
+    }
+}
+
+/// A rule broken by a passphrase, with the words that broke it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation<'a> {
+    /// The broken rule
+    pub rule: &'a Rule,
+    /// The conflicting words (e.g. a repeated word, a group of anagrams or the words missing from
+    /// the dictionary). Empty for rules about the passphrase as a whole.
+    pub words: Vec<String>,
+}
+
+impl<'a> fmt::Display for Violation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rule)?;
+        if !self.words.is_empty() {
+            write!(f, ": {}", self.words.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What counts as a letter when checking words for anagrams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Letters {
+    /// Unicode scalar values, so combining marks are letters of their own
+    #[default]
+    Chars,
+    /// Counts of ASCII bytes in a fixed array, falling back to `Chars` for other words
+    Ascii,
+    /// Grapheme clusters of the NFC normalised word
+    Graphemes,
+}
+
+/// The letters of a word, compared to find anagrams. ASCII counts stay inline to avoid allocating.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Counts([u16; 128]),
+    Chars(Vec<char>),
+    Graphemes(Vec<String>),
+}
+
+/// A set of rules for passphrases
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Policy {
+    rules: Vec<Rule>,
+    letters: Letters,
+    fold_case: bool,
+}
+
+impl Policy {
+    /// A policy with no rules, which every passphrase follows
+    pub fn new() -> Policy {
+        Policy::default()
+    }
+
+    /// Add a rule to the policy
+    pub fn rule(mut self, rule: Rule) -> Policy {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Choose what counts as a letter for `Rule::NoAnagrams`
+    pub fn letters(mut self, letters: Letters) -> Policy {
+        self.letters = letters;
+        self
+    }
+
+    /// Ignore case differences for `Rule::NoAnagrams`
+    pub fn fold_case(mut self, fold_case: bool) -> Policy {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// The anagram key of the word, under the configured letters and case folding
+    fn key(&self, word: &str) -> Key {
+        match self.letters {
+            Letters::Ascii if word.is_ascii() => {
+                let mut counts = [0u16; 128];
+                for b in word.bytes() {
+                    let b = if self.fold_case {
+                        b.to_ascii_lowercase()
+                    } else {
+                        b
+                    };
+                    counts[b as usize] = counts[b as usize].saturating_add(1);
+                }
+                Key::Counts(counts)
+            }
+            Letters::Ascii | Letters::Chars => {
+                let mut key: Vec<char> = if self.fold_case {
+                    word.chars().flat_map(char::to_lowercase).collect()
+                } else {
+                    word.chars().collect()
+                };
+                key.sort();
+                Key::Chars(key)
+            }
+            Letters::Graphemes => {
+                let word: String = if self.fold_case {
+                    word.to_lowercase().nfc().collect()
+                } else {
+                    word.nfc().collect()
+                };
+                let mut key: Vec<String> = word.graphemes(true).map(String::from).collect();
+                key.sort();
+                Key::Graphemes(key)
+            }
+        }
+    }
+
+    /// `O(m log m)` for `m` words. Check the passphrase against each rule, in the order they were
+    /// added, and report every violation.
+    pub fn check(&self, line: &str) -> Vec<Violation<'_>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let mut violations = Vec::new();
+
+        for rule in self.rules.iter() {
+            let mut broken = |words: Vec<String>| violations.push(Violation { rule, words });
+            match rule {
+                Rule::NoRepeats => conflicts(&words, |w| w.to_string())
+                    .into_iter()
+                    .for_each(&mut broken),
+                Rule::NoAnagrams => conflicts(&words, |w| self.key(w))
+                    .into_iter()
+                    .for_each(&mut broken),
+                Rule::MinWords(n) if words.len() < *n => {
+                    broken(words.iter().map(|w| w.to_string()).collect())
+                }
+                Rule::Dictionary(dictionary) => {
+                    let unknown: Vec<String> = words
+                        .iter()
+                        .filter(|w| !dictionary.contains(**w))
+                        .map(|w| w.to_string())
+                        .collect();
+                    if !unknown.is_empty() {
+                        broken(unknown)
+                    }
+                }
+                Rule::MinEntropy(bits) if entropy(&words) < *bits => broken(Vec::new()),
+                _ => {}
+            }
+        }
+
+        violations
+    }
+
+    /// `O(n)` Check every line of the input and report the (1-based) line numbers that break the
+    /// policy, along with their violations
+    pub fn audit<'a>(&'a self, input: &str) -> Vec<(usize, Vec<Violation<'a>>)> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, self.check(line)))
+            .filter(|(_, violations)| !violations.is_empty())
+            .collect()
+    }
+}
+
+/// Group the words by the given key and return each group with more than one word, in order of
+/// first appearance
+fn conflicts<K, F>(words: &[&str], key: F) -> Vec<Vec<String>>
+where
+    K: Eq + ::std::hash::Hash,
+    F: Fn(&str) -> K,
+{
+    let mut groups: HashMap<K, Vec<usize>> = HashMap::new();
+    for (i, word) in words.iter().enumerate() {
+        groups.entry(key(word)).or_default().push(i);
+    }
+
+    let mut conflicts: Vec<Vec<usize>> = groups.into_values().collect();
+    conflicts.retain(|g| g.len() > 1);
+    conflicts.sort();
+    conflicts
+        .into_iter()
+        .map(|g| g.into_iter().map(|i| words[i].to_string()).collect())
+        .collect()
+}
+
+/// Estimate the entropy of a passphrase (in bits) as its length times the Shannon entropy of its
+/// characters
+fn entropy(words: &[&str]) -> f64 {
+    let mut counts = BTreeMap::new();
+    for c in words.iter().flat_map(|w| w.chars()) {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+
+    let total: u32 = counts.values().sum();
+    let total = f64::from(total);
+    counts
+        .values()
+        .map(|&n| {
+            let p = f64::from(n) / total;
+            -p * p.log2() * total
+        }).sum()
 }
 
 #[test]
 fn examples() {
-    assert!(no_repeat(&"aa bb cc dd ee"));
-    assert!(!no_repeat(&"aa bb cc dd aa"));
-    assert!(no_repeat(&"aa bb cc dd aaa"));
-    assert!(no_anagram(&"abcde fghij"));
-    assert!(!no_anagram(&"abcde xyz ecdab"));
-    assert!(no_anagram(&"a ab abc abd abf abj"));
-    assert!(no_anagram(&"iiii oiii ooii oooi oooo"));
-    assert!(!no_anagram(&"oiii ioii iioi iiio"));
+    assert!(no_repeat("aa bb cc dd ee"));
+    assert!(!no_repeat("aa bb cc dd aa"));
+    assert!(no_repeat("aa bb cc dd aaa"));
+    assert!(no_anagram("abcde fghij"));
+    assert!(!no_anagram("abcde xyz ecdab"));
+    assert!(no_anagram("a ab abc abd abf abj"));
+    assert!(no_anagram("iiii oiii ooii oooi oooo"));
+    assert!(!no_anagram("oiii ioii iioi iiio"));
+}
+
+#[test]
+fn policies() {
+    let dictionary = ["correct", "horse", "battery", "staple", "esroh"];
+    let policy = Policy::new()
+        .rule(Rule::NoRepeats)
+        .rule(Rule::NoAnagrams)
+        .rule(Rule::MinWords(4))
+        .rule(Rule::Dictionary(
+            dictionary.iter().map(|w| w.to_string()).collect(),
+        )).rule(Rule::MinEntropy(60.0));
+
+    assert!(policy.check("correct horse battery staple").is_empty());
+
+    let report: Vec<String> = policy
+        .check("horse esroh horse zebra")
+        .iter()
+        .map(Violation::to_string)
+        .collect();
+    assert_eq!(
+        vec![
+            "no repeated words: horse horse",
+            "no anagrams: horse esroh horse",
+            "dictionary words only: zebra",
+            "at least 60 bits of entropy",
+        ],
+        report
+    );
+
+    let short = policy.check("staple staple");
+    assert_eq!(&Rule::MinWords(4), short[2].rule);
+    assert_eq!(vec!["staple", "staple"], short[2].words);
+    assert_eq!(4, short.len());
+
+    let repeats = Policy::new().rule(Rule::NoRepeats);
+    let audit = repeats.audit("aa bb\naa aa\ncc");
+    assert_eq!(1, audit.len());
+    assert_eq!(2, audit[0].0);
+}
+
+#[test]
+fn letters() {
+    let anagrams =
+        |policy: Policy, line: &str| !policy.rule(Rule::NoAnagrams).check(line).is_empty();
+
+    // "é" is composed in the first word and decomposed in the second
+    let composed = "caf\u{e9} \u{e9}fac";
+    let decomposed = "caf\u{e9} e\u{301}fac";
+    assert!(anagrams(Policy::new(), composed));
+    assert!(!anagrams(Policy::new(), decomposed));
+    assert!(anagrams(
+        Policy::new().letters(Letters::Graphemes),
+        decomposed
+    ));
+
+    // Sorting chars moves the accent from "e" onto "a"
+    let moved = "e\u{301}a a\u{301}e";
+    assert!(anagrams(Policy::new(), moved));
+    assert!(!anagrams(Policy::new().letters(Letters::Graphemes), moved));
+
+    for letters in [Letters::Chars, Letters::Ascii, Letters::Graphemes].iter() {
+        let policy = Policy::new().letters(*letters);
+        assert!(!anagrams(policy.clone(), "Listen silent"));
+        assert!(anagrams(policy.clone().fold_case(true), "Listen silent"));
+        assert!(anagrams(policy.clone(), "oiii ioii iioi iiio"));
+        assert!(!anagrams(policy, "iiii oiii ooii oooi oooo"));
+    }
+    assert!(anagrams(
+        Policy::new().letters(Letters::Graphemes).fold_case(true),
+        "\u{c9}A a\u{e9}"
+    ));
 }
 
 #[test]
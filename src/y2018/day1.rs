@@ -3,46 +3,144 @@
 //! Chronal Calibration ([Statement](https://adventofcode.com/2018/day/1)).
 
 use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 
-/// `O(n)` one-liner with no additional space
-pub fn part1(input: &[i32]) -> i32 {
-    input.iter().sum()
+/// The running frequency left the range of `i64`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "frequency overflow")
+    }
+}
+
+impl Error for Overflow {}
+
+/// `O(n)` checked sum with no additional space
+pub fn part1(input: &[i64]) -> Result<i64, Overflow> {
+    input
+        .iter()
+        .try_fold(0i64, |sum, &x| sum.checked_add(x))
+        .ok_or(Overflow)
 }
 
-/// `O(n)` with `O(n)` space for a hash-set to track seen numbers.
-pub fn part2(input: &[i32]) -> i32 {
-    let mut current: i32 = 0;
+/// `O(n log n)` with `O(n)` space. Find the first frequency reached twice, or `None` if no frequency
+/// ever repeats.
+///
+/// After `k` full passes the `i`th frequency is `s[i] + k*t`, where `s` are the prefix sums of the
+/// first pass and `t` is the total drift. If the first pass has no repeat, a later `s[i] + d*t` can
+/// only repeat an `s[j]` with `s[j] - s[i]` a multiple of `t`, so grouping the prefix sums by their
+/// residue mod `t` and pairing each with its nearest neighbor in the direction of the drift gives
+/// every candidate repeat without simulating the passes. The first pass is checked for overflow,
+/// and so is every frequency reached on the later passes before the first repeat.
+pub fn part2(input: &[i64]) -> Result<Option<i64>, Overflow> {
+    let mut current: i64 = 0;
+    let mut sums = Vec::with_capacity(input.len());
     let mut seen = HashSet::new();
-    seen.insert(current);
 
-    for x in input.iter().cycle() {
-        current += x;
-        if !seen.insert(current) {
-            return current;
+    // The first pass, which also catches every repeat when there is no drift
+    for x in input {
+        seen.insert(current);
+        sums.push(current);
+        current = current.checked_add(*x).ok_or(Overflow)?;
+        if seen.contains(&current) {
+            return Ok(Some(current));
         }
     }
-    unreachable!()
+
+    let drift = i128::from(current);
+    if drift == 0 {
+        return Ok(None);
+    }
+
+    // Order by residue, then by frequency
+    let mut order: Vec<(i128, i64, usize)> = sums
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| (i128::from(s).rem_euclid(drift), s, i))
+        .collect();
+    order.sort_unstable();
+
+    // A frequency `s[i]` reaches its neighbor `s[j]` (in the direction of the drift) after `d`
+    // passes, i.e. at step `d*n + i`
+    let n = input.len() as i128;
+    let first = order
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0)
+        .map(|pair| {
+            let (i, j) = if drift > 0 {
+                (pair[0].2, pair[1].2)
+            } else {
+                (pair[1].2, pair[0].2)
+            };
+            let passes = (i128::from(sums[j]) - i128::from(sums[i])) / drift;
+            (passes * n + i as i128, passes, i, sums[j])
+        }).min();
+
+    let (passes, i, frequency) = match first {
+        Some((_, passes, i, frequency)) => (passes, i, frequency),
+        None => return Ok(None),
+    };
+
+    // The furthest frequencies (in the direction of the drift) reached before the repeat are on the
+    // last full pass and on the partial pass up to `s[i]`
+    let furthest = |sums: &[i64]| {
+        let sums = sums.iter().map(|&s| i128::from(s));
+        let furthest = if drift > 0 { sums.max() } else { sums.min() };
+        furthest.unwrap_or(0)
+    };
+    let range = i128::from(i64::MIN)..=i128::from(i64::MAX);
+    if !range.contains(&(furthest(&sums) + (passes - 1) * drift))
+        || !range.contains(&(furthest(&sums[..=i]) + passes * drift))
+    {
+        return Err(Overflow);
+    }
+
+    Ok(Some(frequency))
 }
 
 #[test]
 fn examples() {
-    assert_eq!(part1(&[1, 1, 1]), 3);
-    assert_eq!(part1(&[1, 1, -2]), 0);
-    assert_eq!(part1(&[-1, -2, -3]), -6);
-    assert_eq!(part2(&[1, -1]), 0);
-    assert_eq!(part2(&[3, 3, 4, -2, -4]), 10);
-    assert_eq!(part2(&[-6, 3, 8, 5, -6]), 5);
-    assert_eq!(part2(&[7, 7, -2, -7, -4]), 14);
+    assert_eq!(part1(&[1, 1, 1]), Ok(3));
+    assert_eq!(part1(&[1, 1, -2]), Ok(0));
+    assert_eq!(part1(&[-1, -2, -3]), Ok(-6));
+    assert_eq!(part2(&[1, -1]), Ok(Some(0)));
+    assert_eq!(part2(&[3, 3, 4, -2, -4]), Ok(Some(10)));
+    assert_eq!(part2(&[-6, 3, 8, 5, -6]), Ok(Some(5)));
+    assert_eq!(part2(&[7, 7, -2, -7, -4]), Ok(Some(14)));
+    assert_eq!(part2(&[1, 1]), Ok(None));
+    assert_eq!(part2(&[]), Ok(None));
+    assert_eq!(part2(&[0]), Ok(Some(0)));
+    assert_eq!(part2(&[-1, 2, 1]), Ok(Some(1)));
+    assert_eq!(part2(&[1_000_000, -999_999]), Ok(Some(1_000_000)));
+}
+
+#[test]
+fn overflows() {
+    let big = i64::from(i32::MAX);
+    assert_eq!(part1(&[big, big]), Ok(2 * big));
+    assert_eq!(part1(&[i64::MAX, 1]), Err(Overflow));
+    assert_eq!(part1(&[i64::MAX, 1, -1]), Err(Overflow));
+    assert_eq!(part2(&[i64::MAX, 1]), Err(Overflow));
+    assert_eq!(part2(&[i64::MAX, i64::MIN + 1]), Ok(Some(0)));
+    // The second pass goes from -1 to `i64::MIN - 1`
+    assert_eq!(part2(&[i64::MIN, i64::MAX]), Err(Overflow));
+    assert_eq!(part2(&[i64::MAX - 5, -(i64::MAX - 5) + 1]), Err(Overflow));
+    assert_eq!(part2(&[i64::MIN + 5, -(i64::MIN + 5) - 1]), Err(Overflow));
+    assert_eq!(part2(&[1 << 62, -(1 << 62) + (1 << 32)]), Ok(Some(1 << 62)));
+    assert_eq!(part2(&[(1 << 62) + (1 << 32), -(1 << 62)]), Err(Overflow));
 }
 
 #[test]
 fn solution() {
     use std::str::FromStr;
     let input = include_str!("input/1");
-    let numbers: Vec<i32> = input
+    let numbers: Vec<i64> = input
         .lines()
-        .filter_map(|s| i32::from_str(s).ok())
+        .filter_map(|s| i64::from_str(s).ok())
         .collect();
-    assert_eq!(part1(&numbers), 525);
-    assert_eq!(part2(&numbers), 75749);
+    assert_eq!(part1(&numbers), Ok(525));
+    assert_eq!(part2(&numbers), Ok(Some(75749)));
 }
@@ -2,32 +2,137 @@
 // Licensed under the MIT License <https://opensource.org/licenses/MIT>
 //! Inventory Management System ([Statement](https://adventofcode.com/2018/day/2)).
 
+use std::cmp::{max, min};
 use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use super::super::TRUST;
 
 /// Find the checksum of the input defined as the product of the number of lines in the input that
 /// have 2 characters repeated with the number of lines with three characters repeated.
-/// `O(n*m)` for `n` lines with `m` chars each. `O(1)` extra space for a map to count characters.
+/// `O(n*m)` for `n` lines with `m` chars each. `O(1)` extra space to count characters.
 pub fn part1(lines: &[&str]) -> u32 {
-    let mut twice = 0;
-    let mut thrice = 0;
+    u32::try_from(checksum(lines, &[2, 3])).expect(TRUST)
+}
 
-    for line in lines {
-        let (two, three) = repeated(*line);
-        if two {
-            twice += 1;
+/// The number of times each character appears in a line. ASCII characters are counted in a fixed
+/// array, and any other characters in a map.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// Counts of ASCII characters
+    ascii: [u32; 128],
+    /// Counts of other characters
+    other: HashMap<char, u32>,
+}
+
+impl Signature {
+    /// `O(m)` Count the characters in the line
+    pub fn new(line: &str) -> Signature {
+        let mut signature = Signature {
+            ascii: [0; 128],
+            other: HashMap::new(),
+        };
+        for c in line.chars() {
+            if c.is_ascii() {
+                signature.ascii[c as usize] += 1;
+            } else {
+                *signature.other.entry(c).or_insert(0) += 1;
+            }
         }
-        if three {
-            thrice += 1;
+        signature
+    }
+
+    /// The number of times the character appears
+    pub fn count(&self, c: char) -> u32 {
+        if c.is_ascii() {
+            self.ascii[c as usize]
+        } else {
+            self.other.get(&c).cloned().unwrap_or(0)
         }
     }
 
-    (twice * thrice)
+    /// Check if any character appears exactly the given number of times
+    pub fn has(&self, multiplicity: u32) -> bool {
+        multiplicity > 0 && self.counts().any(|n| n == multiplicity)
+    }
+
+    /// The counts of the characters that appear
+    fn counts(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ascii
+            .iter()
+            .chain(self.other.values())
+            .cloned()
+            .filter(|&n| n > 0)
+    }
+}
+
+/// `O(n*m)` The product, over the given multiplicities, of the number of lines with some character
+/// appearing exactly that many times. The puzzle's checksum uses `[2, 3]`.
+pub fn checksum(lines: &[&str], multiplicities: &[u32]) -> u64 {
+    let mut lines_with = vec![0u64; multiplicities.len()];
+    for line in lines {
+        let signature = Signature::new(line);
+        for (count, multiplicity) in lines_with.iter_mut().zip(multiplicities.iter()) {
+            if signature.has(*multiplicity) {
+                *count += 1;
+            }
+        }
+    }
+    lines_with.into_iter().product()
 }
 
 /// Compute the "box identifier" from the input by finding 2 lines that differ in exactly 1 index
-/// and returning all matching characters in sequence. `O(n^2*m)` to consider each pair of lines in
-/// the input and compare `m` characters to find differences. No additional space.
+/// and returning all matching characters in sequence. `O(n*m)` expected time and `O(n*m)` space.
+///
+/// Two lines differ in exactly one index `p` if and only if they are equal with index `p` masked
+/// out. Each line is hashed once per masked index using prefix hashes, so every mask costs `O(1)`,
+/// and lines sharing a masked hash are compared to rule out collisions and exact duplicates.
 pub fn part2(lines: &[&str]) -> String {
+    // Hashes are polynomials in this base, modulo 2^64
+    const BASE: u64 = 0x0100_0000_01b3;
+
+    let mut seen: HashMap<(usize, usize, u64), Vec<usize>> = HashMap::new();
+    let mut powers = vec![1u64];
+
+    for (index, line) in lines.iter().enumerate() {
+        let chars: Vec<u64> = line.chars().map(|c| u64::from(c) + 1).collect();
+        let m = chars.len();
+
+        // Prefix hashes and powers of the base
+        let mut prefix = vec![0u64; m + 1];
+        for (i, c) in chars.iter().enumerate() {
+            prefix[i + 1] = prefix[i].wrapping_mul(BASE).wrapping_add(*c);
+        }
+        while powers.len() <= m {
+            let next = powers[powers.len() - 1].wrapping_mul(BASE);
+            powers.push(next);
+        }
+
+        for p in 0..m {
+            // Hash of the line with index `p` removed: the prefix before it, shifted over the
+            // suffix after it
+            let suffix = prefix[m].wrapping_sub(prefix[p + 1].wrapping_mul(powers[m - p - 1]));
+            let masked = prefix[p]
+                .wrapping_mul(powers[m - p - 1])
+                .wrapping_add(suffix);
+
+            let candidates = seen.entry((p, m, masked)).or_default();
+            for other in candidates.iter() {
+                if let Some(id) = extract(lines[*other], line) {
+                    return id;
+                }
+            }
+            candidates.push(index);
+        }
+    }
+
+    unreachable!()
+}
+
+/// Same as [part2], by comparing every pair of lines. `O(n^2*m)` to consider each pair of lines in
+/// the input and compare `m` characters to find differences. No additional space. This is the
+/// original nested loop (which visits each pair twice), kept as the baseline for the benchmark.
+pub fn part2_pairwise(lines: &[&str]) -> String {
     for a in lines {
         for b in lines {
             if let Some(id) = extract(a, b) {
@@ -40,30 +145,153 @@ pub fn part2(lines: &[&str]) -> String {
 
 /// Take two strings and if they differ at exactly one index, return the equal chars in sequence.
 pub fn extract(a: &str, b: &str) -> Option<String> {
-    debug_assert!(a.len() == b.len());
-
-    match a.chars().zip(b.chars()).filter(|x| x.0 != x.1).count() {
-        1 => Some(
-            a.chars()
-                .zip(b.chars())
-                .filter(|x| x.0 == x.1)
-                .map(|x| x.0)
-                .collect(),
-        ),
+    if a.chars().count() != b.chars().count() {
+        return None;
+    }
+
+    match hamming_within(a, b, 1) {
+        Some((1, common)) => Some(common),
         _ => None,
     }
 }
 
-/// Test if the line has characters that repeat the 2 or 3 times
-pub fn repeated(line: &str) -> (bool, bool) {
-    let mut counts = HashMap::new();
-    line.chars()
-        .for_each(|c| *counts.entry(c).or_insert(0) += 1);
+/// A pair of IDs within a distance of each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the first ID
+    pub first: usize,
+    /// Index of the second ID
+    pub second: usize,
+    /// The distance between them
+    pub distance: usize,
+    /// The characters they have in common, in sequence
+    pub common: String,
+}
+
+/// `O(n^2*d)` for `d` the cost of the metric. Find all pairs of IDs within `k` of each other by the
+/// given metric (e.g. [hamming_within] or [levenshtein_within]).
+pub fn search<F>(ids: &[&str], k: usize, metric: F) -> Vec<Match>
+where
+    F: Fn(&str, &str, usize) -> Option<(usize, String)>,
+{
+    let mut matches = Vec::new();
+    for (first, a) in ids.iter().enumerate() {
+        for (second, b) in ids.iter().enumerate().skip(first + 1) {
+            if let Some((distance, common)) = metric(a, b, k) {
+                matches.push(Match {
+                    first,
+                    second,
+                    distance,
+                    common,
+                });
+            }
+        }
+    }
+    matches
+}
 
-    let twice = counts.iter().any(|e| *e.1 == 2);
-    let thrice = counts.iter().any(|e| *e.1 == 3);
+/// `O(m)` The number of substituted positions between two IDs (with the extra characters of the
+/// longer ID counted as substitutions) and the characters equal at the same position, if the
+/// number is at most `k`.
+pub fn hamming_within(a: &str, b: &str, k: usize) -> Option<(usize, String)> {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut distance = max(a.len(), b.len()) - min(a.len(), b.len());
+    let mut common = String::new();
 
-    (twice, thrice)
+    for (x, y) in a.iter().zip(b.iter()) {
+        if x == y {
+            common.push(*x);
+        } else {
+            distance += 1;
+        }
+        if distance > k {
+            return None;
+        }
+    }
+
+    if distance > k {
+        None
+    } else {
+        Some((distance, common))
+    }
+}
+
+/// `O(m*k)` The edit distance (insertions, deletions and substitutions) between two IDs and the
+/// characters kept by a shortest edit, if the distance is at most `k`. Only the diagonal band of
+/// width `2k+1` of the edit table is stored, since cells outside it are always more than `k`.
+pub fn levenshtein_within(a: &str, b: &str, k: usize) -> Option<(usize, String)> {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (n, m) = (a.len(), b.len());
+    if max(n, m) - min(n, m) > k {
+        return None;
+    }
+
+    // Cell `(i, j)` of the edit table is at `i * width + j + k - i` in the band, and cells outside
+    // the band stay above `k`
+    let far = k + 1;
+    let width = 2 * k + 1;
+    let mut band = vec![far; (n + 1) * width];
+    let cell = |i: usize, j: usize| {
+        if j + k < i || j > i + k {
+            None
+        } else {
+            Some(i * width + j + k - i)
+        }
+    };
+    let at = |band: &[usize], i: usize, j: usize| cell(i, j).map_or(far, |c| band[c]);
+
+    for i in 0..=n {
+        let mut best = far;
+        for j in i.saturating_sub(k)..=min(m, i + k) {
+            let value = if i == 0 {
+                j
+            } else if j == 0 {
+                i
+            } else {
+                let substitute = at(&band, i - 1, j - 1) + usize::from(a[i - 1] != b[j - 1]);
+                let delete = at(&band, i - 1, j) + 1;
+                let insert = at(&band, i, j - 1) + 1;
+                min(far, min(substitute, min(delete, insert)))
+            };
+            band[cell(i, j).expect("cell in band")] = value;
+            best = min(best, value);
+        }
+        if best > k {
+            return None;
+        }
+    }
+
+    let distance = at(&band, n, m);
+    if distance > k {
+        return None;
+    }
+
+    // Walk back along a shortest edit, keeping the characters it matched
+    let mut common = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let here = at(&band, i, j);
+        if i > 0 && j > 0 && a[i - 1] == b[j - 1] && here == at(&band, i - 1, j - 1) {
+            common.push(a[i - 1]);
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && here == at(&band, i - 1, j - 1) + 1 {
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && here == at(&band, i - 1, j) + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+
+    Some((distance, common.into_iter().rev().collect()))
+}
+
+/// Test if the line has characters that repeat the 2 or 3 times
+pub fn repeated(line: &str) -> (bool, bool) {
+    let signature = Signature::new(line);
+    (signature.has(2), signature.has(3))
 }
 
 /// Read the given input into lines
@@ -102,11 +330,106 @@ fn examples() {
 
     assert_eq!(part1(&one), 12);
     assert_eq!(part2(&two), String::from("fgij"));
+    assert_eq!(part2_pairwise(&two), String::from("fgij"));
+    assert_eq!(part2(&["abc", "abc", "xyz", "abd"]), String::from("ab"));
+}
+
+#[test]
+fn distances() {
+    assert_eq!(
+        Some((1, String::from("fgij"))),
+        hamming_within("fghij", "fguij", 1)
+    );
+    assert_eq!(
+        Some((2, String::from("abc"))),
+        hamming_within("abcd", "abcxy", 2)
+    );
+    assert_eq!(None, hamming_within("abcd", "abcxy", 1));
+    assert_eq!(None, extract("abc", "ab"));
+
+    assert_eq!(
+        Some((0, String::from("same"))),
+        levenshtein_within("same", "same", 0)
+    );
+    assert_eq!(
+        Some((1, String::from("abde"))),
+        levenshtein_within("abcde", "abde", 1)
+    );
+    assert_eq!(
+        Some((3, String::from("ittn"))),
+        levenshtein_within("kitten", "sitting", 3)
+    );
+    assert_eq!(None, levenshtein_within("kitten", "sitting", 2));
+    assert_eq!(None, levenshtein_within("a", "abcd", 2));
+    assert_eq!(
+        Some((1, String::from("ab"))),
+        levenshtein_within("ab", "xab", 1)
+    );
+    assert_eq!(Some((2, String::new())), levenshtein_within("ab", "", 2));
+    assert_eq!(Some((2, String::new())), levenshtein_within("", "ab", 2));
+    assert_eq!(None, levenshtein_within("ab", "", 1));
+    assert_eq!(Some((0, String::new())), levenshtein_within("", "", 0));
+    assert_eq!(
+        vec![Match {
+            first: 0,
+            second: 1,
+            distance: 2,
+            common: String::new(),
+        }],
+        search(&["ab", ""], 2, levenshtein_within)
+    );
+    let long = "ab".repeat(20_000);
+    assert_eq!(
+        Some((1, long.clone())),
+        levenshtein_within(&long, &format!("{}x", long), 1)
+    );
+
+    let skus = ["SKU-1001", "SKU-1002", "SKU-10012", "SKU-2001"];
+    let pairs = |matches: Vec<Match>| -> Vec<(usize, usize, usize)> {
+        matches
+            .into_iter()
+            .map(|m| (m.first, m.second, m.distance))
+            .collect()
+    };
+    assert_eq!(
+        vec![
+            (0, 1, 1),
+            (0, 2, 1),
+            (0, 3, 1),
+            (1, 2, 2),
+            (1, 3, 2),
+            (2, 3, 2)
+        ],
+        pairs(search(&skus, 2, hamming_within))
+    );
+    assert_eq!(
+        vec![(0, 1, 1), (0, 2, 1), (0, 3, 1), (1, 2, 1)],
+        pairs(search(&skus, 1, levenshtein_within))
+    );
+}
+
+#[test]
+fn signatures() {
+    let signature = Signature::new("bababc");
+    assert_eq!(3, signature.count('b'));
+    assert_eq!(0, signature.count('z'));
+    assert!(signature.has(2) && signature.has(3) && !signature.has(4) && !signature.has(0));
+
+    let unicode = Signature::new("ééaé");
+    assert_eq!(3, unicode.count('é'));
+    assert!(unicode.has(1) && unicode.has(3) && !unicode.has(2));
+
+    let lines = ["aabbbcccc", "aaaa", "abab", "äää"];
+    assert_eq!(2 * 2 * 2, checksum(&lines, &[2, 3, 4]));
+    assert_eq!(1, checksum(&lines, &[]));
+    assert_eq!(0, checksum(&lines, &[5]));
 }
 
 #[test]
 fn solution() {
     let input: Vec<&str> = read(include_str!("input/2"));
     assert_eq!(part1(&input), 7192);
+    assert_eq!(checksum(&input, &[2, 3]), 7192);
     assert_eq!(part2(&input), "mbruvapghxlzycbhmfqjonsie");
+    assert_eq!(part2_pairwise(&input), "mbruvapghxlzycbhmfqjonsie");
 }
@@ -4,9 +4,15 @@
 //! Input size parameters `n`: Number of claims and `m`: dimension of the fabric grid.
 
 use std::cmp::max;
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
 use std::str::FromStr;
 
+use super::super::TRUST;
+
 /// A single claim
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Claim {
     /// The identifier
     id: usize,
@@ -20,37 +26,128 @@ pub struct Claim {
     h: usize,
 }
 
+/// A counter for the number of claims stacked on a square. Increments saturate at the maximum so a
+/// narrow counter never wraps around and makes a heavily claimed square look unclaimed.
+pub trait Depth: Copy + Default + Ord {
+    /// One more claim on the square, saturating at the maximum
+    fn increment(self) -> Self;
+
+    /// The number of claims on the square
+    fn count(self) -> usize;
+}
+
+/// Implement the depth counter for the unsigned integer types
+macro_rules! depth {
+    ($($t:ty),*) => {$(
+        impl Depth for $t {
+            fn increment(self) -> Self {
+                self.saturating_add(1)
+            }
+
+            fn count(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+
+depth!(u8, u16, u32, u64, usize);
+
 /// The entire fabric
-pub struct Fabric {
+pub struct Fabric<C = u32> {
     /// The claims
     claims: Vec<Claim>,
     /// The overlap tracking grid
-    overlaps: Vec<Vec<u8>>,
+    overlaps: Vec<Vec<C>>,
 }
 
-/// Read a single claim from the form
-pub fn read_claim(input: &str) -> Claim {
-    // Expecting  `#1 @ 1,3: 4x4`
-    let num: Vec<usize> = input
-        .split(|c| "#@,:x ".contains(c))
-        .filter_map(|c| usize::from_str(c).ok())
-        .collect();
+/// Reasons a claim fails to parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseClaimError {
+    /// The claim does not follow the `#id @ x,y: wxh` layout
+    Layout,
+    /// The named field is not a number
+    Number(&'static str, ParseIntError),
+}
 
-    // Must have 5 numbers
-    debug_assert!(num.len() == 5);
+impl fmt::Display for ParseClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseClaimError::Layout => write!(f, "expected a claim like `#1 @ 1,3: 4x4`"),
+            ParseClaimError::Number(field, e) => write!(f, "invalid {}: {}", field, e),
+        }
+    }
+}
 
-    // Unpack
-    Claim {
-        id: num[0],
-        x: num[1],
-        y: num[2],
-        l: num[3],
-        h: num[4],
+impl Error for ParseClaimError {}
+
+/// Parse a claim written as `#1 @ 1,3: 4x4`
+impl FromStr for Claim {
+    type Err = ParseClaimError;
+
+    fn from_str(input: &str) -> Result<Claim, ParseClaimError> {
+        // Split the claim into its parts
+        fn split(s: &str, at: char) -> Result<(&str, &str), ParseClaimError> {
+            s.split_once(at).ok_or(ParseClaimError::Layout)
+        }
+
+        let rest = input
+            .trim()
+            .strip_prefix('#')
+            .ok_or(ParseClaimError::Layout)?;
+        let (id, rest) = split(rest, '@')?;
+        let (origin, size) = split(rest, ':')?;
+        let (x, y) = split(origin, ',')?;
+        let (l, h) = split(size, 'x')?;
+
+        // Read the numbers
+        let number = |field, s: &str| {
+            usize::from_str(s.trim()).map_err(|e| ParseClaimError::Number(field, e))
+        };
+
+        Ok(Claim {
+            id: number("id", id)?,
+            x: number("x", x)?,
+            y: number("y", y)?,
+            l: number("width", l)?,
+            h: number("height", h)?,
+        })
+    }
+}
+
+/// Write a claim in its canonical `#1 @ 1,3: 4x4` form
+impl fmt::Display for Claim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{} @ {},{}: {}x{}",
+            self.id, self.x, self.y, self.l, self.h
+        )
     }
 }
 
+/// Write all claims on the fabric, one per line, in a form [read] accepts
+impl<C> fmt::Display for Fabric<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for claim in self.claims.iter() {
+            writeln!(f, "{}", claim)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a single claim from the form
+pub fn read_claim(input: &str) -> Claim {
+    input.parse().expect(TRUST)
+}
+
 /// Read all claims from the input lines. `O(n)` with `O(m^2)` additional space.
 pub fn read(input: &str) -> Fabric {
+    read_as(input)
+}
+
+/// Read all claims from the input lines, counting overlaps with the chosen [Depth] type.
+pub fn read_as<C: Depth>(input: &str) -> Fabric<C> {
     let mut dimensions = (0, 0);
 
     // Read claims and update the dimensions
@@ -65,13 +162,13 @@ pub fn read(input: &str) -> Fabric {
         }).collect();
 
     // Create an overlap tracking grid
-    let mut overlaps = vec![vec![0; dimensions.1]; dimensions.0];
+    let mut overlaps = vec![vec![C::default(); dimensions.1]; dimensions.0];
 
     // Track the overlapping claims
     for claim in claims.iter() {
-        for x in claim.x..claim.x + claim.l {
-            for y in claim.y..claim.y + claim.h {
-                overlaps[x][y] += 1;
+        for column in overlaps[claim.x..claim.x + claim.l].iter_mut() {
+            for square in column[claim.y..claim.y + claim.h].iter_mut() {
+                *square = square.increment();
             }
         }
     }
@@ -81,26 +178,17 @@ pub fn read(input: &str) -> Fabric {
 }
 
 /// Count overlapping squared - `O(m^2)`
-pub fn part1(fabric: &Fabric) -> usize {
-    fabric
-        .overlaps
-        .iter()
-        .flat_map(|v| v.iter())
-        .filter(|c| **c > 1)
-        .count()
+pub fn part1<C: Depth>(fabric: &Fabric<C>) -> usize {
+    squares(fabric).filter(|c| *c > 1).count()
 }
 
 /// Find the only claim that does not overlap. `O(n*m^2)`
-pub fn part2(fabric: &Fabric) -> usize {
+pub fn part2<C: Depth>(fabric: &Fabric<C>) -> usize {
     for claim in fabric.claims.iter() {
-        let mut overlap = false;
-        for x in claim.x..claim.x + claim.l {
-            for y in claim.y..claim.y + claim.h {
-                if fabric.overlaps[x][y] > 1 {
-                    overlap = true
-                }
-            }
-        }
+        let overlap = fabric.overlaps[claim.x..claim.x + claim.l]
+            .iter()
+            .flat_map(|column| column[claim.y..claim.y + claim.h].iter())
+            .any(|square| square.count() > 1);
 
         if !overlap {
             return claim.id;
@@ -110,6 +198,29 @@ pub fn part2(fabric: &Fabric) -> usize {
     0
 }
 
+/// `O(m^2)` The largest number of claims stacked on any single square
+pub fn max_depth<C: Depth>(fabric: &Fabric<C>) -> usize {
+    squares(fabric).max().unwrap_or(0)
+}
+
+/// `O(m^2)` The number of squares at each depth, indexed by the number of claims on the square
+pub fn histogram<C: Depth>(fabric: &Fabric<C>) -> Vec<usize> {
+    let mut cells = vec![0; max_depth(fabric) + 1];
+    for depth in squares(fabric) {
+        cells[depth] += 1;
+    }
+    cells
+}
+
+/// Iterate over the claim counts of all squares on the fabric
+fn squares<C: Depth>(fabric: &Fabric<C>) -> impl Iterator<Item = usize> + '_ {
+    fabric
+        .overlaps
+        .iter()
+        .flat_map(|v| v.iter())
+        .map(|c| c.count())
+}
+
 #[test]
 fn examples() {
     let fabric = read(
@@ -120,7 +231,50 @@ fn examples() {
     ",
     );
     assert_eq!(4, part1(&fabric));
-    assert_eq!(3, part2(&fabric))
+    assert_eq!(3, part2(&fabric));
+    assert_eq!(2, max_depth(&fabric));
+    assert_eq!(vec![17, 28, 4], histogram(&fabric));
+}
+
+#[test]
+fn claims() {
+    let claim: Claim = "#123 @ 3,2: 5x4".parse().unwrap();
+    assert_eq!("#123 @ 3,2: 5x4", claim.to_string());
+    assert_eq!(Ok(claim), "  #123@3,2:5x4 ".parse());
+
+    assert_eq!(
+        Err(ParseClaimError::Layout),
+        "123 @ 3,2: 5x4".parse::<Claim>()
+    );
+    assert_eq!(
+        Err(ParseClaimError::Layout),
+        "#123 @ 3,2 5x4".parse::<Claim>()
+    );
+    match "#1 @ 3,2: 5xq".parse::<Claim>() {
+        Err(ParseClaimError::Number(field, _)) => assert_eq!("height", field),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let fabric = read(include_str!("input/3"));
+    let again = read(&fabric.to_string());
+    assert_eq!(fabric.claims, again.claims);
+    assert_eq!(fabric.to_string(), again.to_string());
+}
+
+#[test]
+fn depths() {
+    let input: String = (1..=300)
+        .map(|id| format!("#{} @ 0,0: 2x1\n", id))
+        .collect();
+
+    let narrow = read_as::<u8>(&input);
+    assert_eq!(2, part1(&narrow));
+    assert_eq!(255, max_depth(&narrow));
+
+    let wide = read(&input);
+    assert_eq!(2, part1(&wide));
+    assert_eq!(300, max_depth(&wide));
+    assert_eq!(2, histogram(&wide)[300]);
 }
 
 #[test]
@@ -3,86 +3,853 @@
 //! Repose Record ([Statement](https://adventofcode.com/2018/day/4)).
 //! Input size parameters `n`: Number of record entries, `g`: Number of guards
 
+use std::cmp::min;
 use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
 
-use super::super::{lines, parse, TRUST};
+use super::super::TRUST;
 
-/// SleepLog is a mapping from guard to the number of times slept in a given minute `O(g)` space
-pub type SleepLog = BTreeMap<usize, Vec<u32>>;
+/// A point in time as written in the records (`1518-11-01 00:05`). The fields are ordered from
+/// most to least significant so the derived ordering is chronological.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    /// Calendar year
+    pub year: u32,
+    /// Month of the year, from 1
+    pub month: u32,
+    /// Day of the month, from 1
+    pub day: u32,
+    /// Hour of the day, from 0
+    pub hour: u32,
+    /// Minute of the hour, from 0
+    pub minute: u32,
+}
 
-/// `O(n)` Read the sleep log from the given input
-pub fn read(input: &str) -> SleepLog {
-    // Read the logs and sort them
-    let mut records: Vec<&str> = lines(input).collect();
-    records.sort();
+/// What happened at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// The guard with the given identifier begins a shift
+    BeginShift(usize),
+    /// The guard on duty falls asleep
+    FallsAsleep,
+    /// The guard on duty wakes up
+    WakesUp,
+}
 
-    // Initialize a new sleep log
-    let mut log = SleepLog::new();
+/// A single record from the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    /// When it happened
+    pub time: Time,
+    /// What happened
+    pub kind: Kind,
+}
 
-    // Initialize the indices
-    let mut guard = 0;
-    let mut since = 0;
+/// Reasons a record fails to parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEventError {
+    /// The record does not start with a valid `[yyyy-mm-dd hh:mm]` timestamp
+    Time(String),
+    /// The text after the timestamp is not a known event
+    Kind(String),
+}
 
-    // Read the log and update the minutes slept
-    for line in records {
-        let numbers: Vec<usize> = parse(line, "[-:] # ").collect();
+impl fmt::Display for ParseEventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseEventError::Time(s) => write!(f, "invalid timestamp `{}`", s),
+            ParseEventError::Kind(s) => write!(f, "unknown event `{}`", s),
+        }
+    }
+}
+
+impl Error for ParseEventError {}
+
+/// Number of days in the given month, using the Gregorian leap year rule
+pub fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Parse a timestamp written as `1518-11-01 00:05`
+impl FromStr for Time {
+    type Err = ParseEventError;
+
+    fn from_str(input: &str) -> Result<Time, ParseEventError> {
+        let invalid = || ParseEventError::Time(input.to_string());
+        let numbers = input
+            .trim()
+            .split(['-', ' ', ':'])
+            .map(u32::from_str)
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| invalid())?;
+
+        match numbers.as_slice() {
+            &[year, month, day, hour, minute]
+                if day >= 1 && day <= days_in_month(year, month) && hour < 24 && minute < 60 =>
+            {
+                Ok(Time {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Minutes in a day
+const DAY: i64 = 24 * 60;
+
+impl Time {
+    /// Minutes since `0000-03-01 00:00`, so the difference of two stamps is the minutes between them
+    pub fn stamp(&self) -> i64 {
+        // Count years from March so the leap day is the last day of the year
+        let (year, month) = if self.month > 2 {
+            (i64::from(self.year), i64::from(self.month) - 3)
+        } else {
+            (i64::from(self.year) - 1, i64::from(self.month) + 9)
+        };
+        let leaps = year / 4 - year / 100 + year / 400;
+        let days = 365 * year + leaps + (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        days * DAY + i64::from(self.hour * 60 + self.minute)
+    }
+
+    /// The time at the given [stamp](Time::stamp)
+    pub fn from_stamp(stamp: i64) -> Time {
+        let (days, minutes) = (stamp.div_euclid(DAY), stamp.rem_euclid(DAY));
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let (year, month) = if month < 10 {
+            (era * 400 + year_of_era, month + 3)
+        } else {
+            (era * 400 + year_of_era + 1, month - 9)
+        };
+        Time {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+            hour: (minutes / 60) as u32,
+            minute: (minutes % 60) as u32,
+        }
+    }
+}
+
+/// Write a timestamp as `1518-11-01 00:05`
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
+    }
+}
+
+/// Parse an event text, e.g. `Guard #10 begins shift`
+impl FromStr for Kind {
+    type Err = ParseEventError;
+
+    fn from_str(input: &str) -> Result<Kind, ParseEventError> {
+        let input = input.trim();
+        let guard = input
+            .strip_prefix("Guard #")
+            .and_then(|s| s.strip_suffix(" begins shift"))
+            .map(usize::from_str);
+
+        match (input, guard) {
+            (_, Some(Ok(id))) => Ok(Kind::BeginShift(id)),
+            ("falls asleep", _) => Ok(Kind::FallsAsleep),
+            ("wakes up", _) => Ok(Kind::WakesUp),
+            _ => Err(ParseEventError::Kind(input.to_string())),
+        }
+    }
+}
+
+/// Parse a record written as `[1518-11-01 00:00] Guard #10 begins shift`
+impl FromStr for Event {
+    type Err = ParseEventError;
+
+    fn from_str(input: &str) -> Result<Event, ParseEventError> {
+        let (time, kind) = input
+            .trim()
+            .strip_prefix('[')
+            .and_then(|s| s.split_once(']'))
+            .ok_or_else(|| ParseEventError::Time(input.to_string()))?;
+
+        Ok(Event {
+            time: time.parse()?,
+            kind: kind.parse()?,
+        })
+    }
+}
+
+/// An event along with the (1-based) input line it was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    /// The input line
+    pub line: usize,
+    /// The event on that line
+    pub event: Event,
+}
+
+/// `O(n log n)` Read the records from the given input and sort them chronologically
+pub fn records(input: &str) -> Result<Vec<Record>, ParseEventError> {
+    let mut records = input
+        .lines()
+        .enumerate()
+        .filter(|(_, s)| !s.trim().is_empty())
+        .map(|(i, s)| s.parse().map(|event| Record { line: i + 1, event }))
+        .collect::<Result<Vec<Record>, _>>()?;
+    records.sort_by_key(|r| r.event.time);
+    Ok(records)
+}
+
+/// `O(n log n)` Read the events from the given input and sort them chronologically
+pub fn events(input: &str) -> Result<Vec<Event>, ParseEventError> {
+    Ok(records(input)?.into_iter().map(|r| r.event).collect())
+}
+
+/// Ways in which a sorted log fails to alternate between sleeping and waking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// A guard sleeps or wakes before any shift has begun
+    NoGuard,
+    /// A guard falls asleep while already asleep
+    AlreadyAsleep,
+    /// A guard wakes up without having fallen asleep
+    NotAsleep,
+    /// A guard falls asleep and the shift ends (or the log ends) before they wake up
+    NeverWakes,
+}
+
+/// A problem found in the log, with the record that exposes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anomaly {
+    /// The guard on duty, if any
+    pub guard: Option<usize>,
+    /// The offending record
+    pub record: Record,
+    /// What is wrong with it
+    pub problem: Problem,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.record.line)?;
+        if let Some(guard) = self.guard {
+            write!(f, "guard #{} ", guard)?;
+        }
+        match self.problem {
+            Problem::NoGuard => write!(f, "no guard on duty"),
+            Problem::AlreadyAsleep => write!(f, "falls asleep while already asleep"),
+            Problem::NotAsleep => write!(f, "wakes up without falling asleep"),
+            Problem::NeverWakes => write!(f, "falls asleep and never wakes up"),
+        }
+    }
+}
+
+/// Reasons a log fails to read in strict mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogError {
+    /// A record failed to parse
+    Parse(ParseEventError),
+    /// The records parsed but do not make a consistent log
+    Anomalies(Vec<Anomaly>),
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::Parse(e) => write!(f, "{}", e),
+            LogError::Anomalies(anomalies) => {
+                let lines: Vec<String> = anomalies.iter().map(Anomaly::to_string).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+        }
+    }
+}
+
+impl Error for LogError {}
+
+impl From<ParseEventError> for LogError {
+    fn from(e: ParseEventError) -> LogError {
+        LogError::Parse(e)
+    }
+}
+
+/// `O(n)` Walk the sorted records shift by shift and report every anomaly
+pub fn validate(records: &[Record]) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    let mut guard = None;
+    let mut asleep: Option<Record> = None;
+
+    let mut report = |guard, record, problem| {
+        anomalies.push(Anomaly {
+            guard,
+            record,
+            problem,
+        })
+    };
+
+    for record in records.iter().cloned() {
+        match (record.event.kind, guard, asleep) {
+            (Kind::BeginShift(id), _, since) => {
+                if let Some(since) = since {
+                    report(guard, since, Problem::NeverWakes);
+                }
+                guard = Some(id);
+                asleep = None;
+            }
+            (_, None, _) => report(None, record, Problem::NoGuard),
+            (Kind::FallsAsleep, _, Some(_)) => report(guard, record, Problem::AlreadyAsleep),
+            (Kind::FallsAsleep, _, None) => asleep = Some(record),
+            (Kind::WakesUp, _, None) => report(guard, record, Problem::NotAsleep),
+            (Kind::WakesUp, _, Some(_)) => asleep = None,
+        }
+    }
+
+    if let Some(since) = asleep {
+        report(guard, since, Problem::NeverWakes);
+    }
+
+    anomalies
+}
+
+/// The part of the day a log watches, split into equal buckets. The default is the puzzle's
+/// midnight hour with one bucket per minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    /// Minute of the day where the first bucket begins
+    start: u32,
+    /// Number of buckets
+    buckets: usize,
+    /// Minutes per bucket
+    resolution: NonZeroU32,
+}
+
+impl Default for Window {
+    fn default() -> Window {
+        Window {
+            start: 0,
+            buckets: 60,
+            resolution: NonZeroU32::MIN,
+        }
+    }
+}
+
+impl Window {
+    /// A window of the given number of buckets of the given number of minutes each, beginning at
+    /// the given minute of the day. There is no such window if it has no buckets or they are empty.
+    pub fn new(start: u32, buckets: usize, resolution: u32) -> Option<Window> {
+        let resolution = NonZeroU32::new(resolution)?;
+        if buckets == 0 {
+            return None;
+        }
+        Some(Window {
+            start: start % DAY as u32,
+            buckets,
+            resolution,
+        })
+    }
+
+    /// A whole day beginning at the given minute of the day, with buckets of the given number of
+    /// minutes. Begin the day away from midnight (e.g. at noon) to keep night shifts in one window.
+    /// There is no such window if the buckets are empty.
+    pub fn day(start: u32, resolution: u32) -> Option<Window> {
+        let buckets = (DAY as u32).div_ceil(resolution.max(1)) as usize;
+        Window::new(start, buckets, resolution)
+    }
+
+    /// Minute of the day where the first bucket begins
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Number of buckets
+    pub fn buckets(&self) -> usize {
+        self.buckets
+    }
+
+    /// Minutes per bucket
+    pub fn resolution(&self) -> u32 {
+        self.resolution.get()
+    }
+
+    /// Minutes covered by the window
+    fn span(&self) -> i64 {
+        min(DAY, self.buckets as i64 * i64::from(self.resolution.get()))
+    }
+
+    /// Minutes from the most recent start of the window to the given time
+    fn offset(&self, stamp: i64) -> i64 {
+        (stamp - i64::from(self.start)).rem_euclid(DAY)
+    }
+
+    /// The bucket the given time falls in, if it falls in the window
+    pub fn bucket(&self, time: Time) -> Option<usize> {
+        self.slot(time.stamp())
+    }
+
+    /// The bucket for the given stamp, if it falls in the window
+    fn slot(&self, stamp: i64) -> Option<usize> {
+        let offset = self.offset(stamp);
+        if offset < self.span() {
+            Some((offset / i64::from(self.resolution.get())) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The end of the first window that ends after the given time
+    pub fn end(&self, time: Time) -> Time {
+        let stamp = time.stamp();
+        let offset = self.offset(stamp);
+        if offset < self.span() {
+            Time::from_stamp(stamp - offset + self.span())
+        } else {
+            Time::from_stamp(stamp + DAY - offset + self.span())
+        }
+    }
+
+    /// Count a nap into the buckets, a minute at a time so naps can wrap around midnight
+    fn tally(&self, nap: &Nap, buckets: &mut [u32]) {
+        for stamp in nap.start.stamp()..nap.end.stamp() {
+            if let Some(slot) = self.slot(stamp) {
+                buckets[slot] += 1;
+            }
+        }
+    }
+}
+
+/// A single nap from the minute a guard falls asleep up to (but excluding) the minute they wake up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nap {
+    /// Falls asleep
+    pub start: Time,
+    /// Wakes up
+    pub end: Time,
+}
+
+impl Nap {
+    /// Number of minutes asleep
+    pub fn minutes(&self) -> u32 {
+        (self.end.stamp() - self.start.stamp()) as u32
+    }
+}
+
+/// A single shift and the naps taken in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shift {
+    /// The guard on duty
+    pub guard: usize,
+    /// When the shift began
+    pub start: Time,
+    /// The end of the watched window, e.g. `01:00` on the day of the shift (or the next day for
+    /// shifts that begin before midnight) for the midnight hour
+    pub end: Time,
+    /// The naps in order
+    pub naps: Vec<Nap>,
+}
+
+impl Shift {
+    /// Minutes from the start of the shift to the end of the watched window
+    pub fn length(&self) -> u32 {
+        (self.end.stamp() - self.start.stamp()) as u32
+    }
+
+    /// Minutes asleep in the shift
+    pub fn asleep(&self) -> u32 {
+        self.naps.iter().map(Nap::minutes).sum()
+    }
+}
+
+/// SleepLog keeps each guard's shifts and the number of times they slept in each bucket of the
+/// watched window. `O(g)` space for the buckets and `O(n)` for the shifts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SleepLog {
+    /// The watched window
+    window: Window,
+    /// Guard to the number of times slept in a given bucket
+    minutes: BTreeMap<usize, Vec<u32>>,
+    /// All shifts in chronological order
+    shifts: Vec<Shift>,
+}
+
+impl SleepLog {
+    /// An empty log watching the midnight hour
+    pub fn new() -> SleepLog {
+        SleepLog::default()
+    }
 
-        if line.contains("Guard") {
-            guard = numbers[5];
+    /// An empty log watching the given window
+    pub fn with_window(window: Window) -> SleepLog {
+        SleepLog {
+            window,
+            ..SleepLog::default()
         }
+    }
+
+    /// The watched window
+    pub fn window(&self) -> Window {
+        self.window
+    }
 
-        if line.contains("falls asleep") {
-            since = numbers[4];
+    /// The number of times the guard slept in each bucket, if they ever slept
+    pub fn get(&self, guard: usize) -> Option<&[u32]> {
+        self.minutes.get(&guard).map(Vec::as_slice)
+    }
+
+    /// `O(g)` Iterate over the guards who slept, and their buckets
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[u32])> {
+        self.minutes.iter().map(|(g, m)| (*g, m.as_slice()))
+    }
+
+    /// All shifts in chronological order
+    pub fn shifts(&self) -> &[Shift] {
+        &self.shifts
+    }
+
+    /// `O(n)` Total minutes asleep for each guard who slept, including any outside the window
+    pub fn totals(&self) -> BTreeMap<usize, u32> {
+        let mut totals = BTreeMap::new();
+        for shift in self.shifts.iter().filter(|s| !s.naps.is_empty()) {
+            *totals.entry(shift.guard).or_insert(0) += shift.asleep();
         }
+        totals
+    }
 
-        if line.contains("wakes up") {
-            let now = numbers[4];
-            let slept = log.entry(guard).or_insert_with(|| vec![0; 60]);
+    /// `O(n)` The number of shifts of each length (in minutes, see [Shift::length])
+    pub fn shift_lengths(&self) -> BTreeMap<u32, usize> {
+        let mut lengths = BTreeMap::new();
+        for shift in self.shifts.iter() {
+            *lengths.entry(shift.length()).or_insert(0) += 1;
+        }
+        lengths
+    }
 
-            for i in since..now {
-                slept[i] += 1
+    /// `O(g)` The bucket (a minute by default) the most guards slept in at least once, with the
+    /// number of guards. Ties go to the earliest bucket.
+    pub fn most_shared_minute(&self) -> Option<(usize, usize)> {
+        let mut guards = vec![0; self.window.buckets];
+        for (_, minutes) in self.iter() {
+            for (shared, slept) in guards.iter_mut().zip(minutes.iter()) {
+                if *slept > 0 {
+                    *shared += 1;
+                }
             }
         }
+
+        guards
+            .into_iter()
+            .enumerate()
+            .filter(|(_, shared)| *shared > 0)
+            .fold(None, |best, (minute, shared)| match best {
+                Some((_, most)) if most >= shared => best,
+                _ => Some((minute, shared)),
+            })
+    }
+
+    /// `O(n)` The longest continuous nap and the guard who took it. Ties go to the earliest nap.
+    pub fn longest_nap(&self) -> Option<(usize, Nap)> {
+        self.shifts
+            .iter()
+            .flat_map(|s| s.naps.iter().map(move |n| (s.guard, *n)))
+            .fold(None, |best, (guard, nap)| match best {
+                Some((_, longest)) if longest.minutes() >= nap.minutes() => best,
+                _ => Some((guard, nap)),
+            })
+    }
+
+    /// Render all shifts as in the puzzle statement, with `#` for asleep and `.` for awake
+    pub fn render(&self) -> String {
+        self.table(|_| true, None)
+    }
+
+    /// Render the guard's shifts as in the puzzle statement, followed by a row with the number of
+    /// times they slept in each bucket (`.` for never and `+` for more than 9 times)
+    pub fn render_guard(&self, guard: usize) -> String {
+        let totals = self.get(guard).map(|minutes| {
+            minutes
+                .iter()
+                .map(|&m| match m {
+                    0 => '.',
+                    1..=9 => (b'0' + m as u8) as char,
+                    _ => '+',
+                }).collect()
+        });
+        self.table(|s| s.guard == guard, totals)
+    }
+
+    /// Render the selected shifts with an optional summary row
+    fn table<F: Fn(&Shift) -> bool>(&self, select: F, summary: Option<String>) -> String {
+        let shifts: Vec<&Shift> = self.shifts.iter().filter(|s| select(s)).collect();
+        let width = shifts
+            .iter()
+            .map(|s| s.guard.to_string().len() + 1)
+            .max()
+            .unwrap_or(2);
+
+        // One header row per digit of the bucket number, most significant first
+        let buckets = self.window.buckets;
+        let digits = (buckets.max(2) - 1).to_string().len() as u32;
+        let mut table = format!("Date   {:w$}  Minute\n", "ID", w = width);
+        for place in (0..digits).rev() {
+            let row: String = (0..buckets)
+                .map(|b| (b'0' + (b / 10usize.pow(place) % 10) as u8) as char)
+                .collect();
+            table += &format!("       {:w$}  {}\n", "", row, w = width);
+        }
+
+        for shift in shifts {
+            let date = Time::from_stamp(shift.end.stamp() - self.window.span());
+            let mut slept = vec![0; buckets];
+            for nap in shift.naps.iter() {
+                self.window.tally(nap, &mut slept);
+            }
+            let cells: String = slept
+                .iter()
+                .map(|&s| if s > 0 { '#' } else { '.' })
+                .collect();
+            let id = format!("#{}", shift.guard);
+            table += &format!(
+                "{:02}-{:02}  {:w$}  {}\n",
+                date.month,
+                date.day,
+                id,
+                cells,
+                w = width
+            );
+        }
+
+        if let Some(summary) = summary {
+            table += &format!("Total  {:w$}  {}\n", "", summary, w = width);
+        }
+        table
+    }
+}
+
+/// `O(n log n)` Read the sleep log for the midnight hour from the given input. Anomalous records are
+/// skipped: a second sleep keeps the first, and a wake with no sleep or a sleep with no wake counts
+/// nothing.
+pub fn read(input: &str) -> SleepLog {
+    read_with(input, Window::default())
+}
+
+/// `O(n log n)` Read the sleep log for the given window from the given input
+pub fn read_with(input: &str, window: Window) -> SleepLog {
+    tally(&records(input).expect(TRUST), window)
+}
+
+/// `O(n log n)` Read the sleep log for the midnight hour from the given input, failing on any
+/// anomaly
+pub fn read_strict(input: &str) -> Result<SleepLog, LogError> {
+    read_strict_with(input, Window::default())
+}
+
+/// `O(n log n)` Read the sleep log for the given window from the given input, failing on any
+/// anomaly
+pub fn read_strict_with(input: &str, window: Window) -> Result<SleepLog, LogError> {
+    let records = records(input)?;
+    let anomalies = validate(&records);
+    if anomalies.is_empty() {
+        Ok(tally(&records, window))
+    } else {
+        Err(LogError::Anomalies(anomalies))
+    }
+}
+
+/// `O(n)` Tally the minutes slept from the sorted records
+fn tally(records: &[Record], window: Window) -> SleepLog {
+    // Initialize a new sleep log
+    let mut log = SleepLog::with_window(window);
+
+    // Initialize the indices
+    let mut guard = None;
+    let mut since = None;
+
+    // Read the log and update the minutes slept
+    for record in records {
+        let now = record.event.time;
+
+        match (record.event.kind, guard, since) {
+            (Kind::BeginShift(id), _, _) => {
+                guard = Some(id);
+                since = None;
+                log.shifts.push(Shift {
+                    guard: id,
+                    start: now,
+                    end: window.end(now),
+                    naps: Vec::new(),
+                });
+            }
+            (Kind::FallsAsleep, Some(_), None) => since = Some(now),
+            (Kind::WakesUp, Some(id), Some(start)) => {
+                let nap = Nap { start, end: now };
+                let slept = log
+                    .minutes
+                    .entry(id)
+                    .or_insert_with(|| vec![0; window.buckets]);
+                window.tally(&nap, slept);
+                since = None;
+
+                log.shifts.last_mut().expect(TRUST).naps.push(nap);
+            }
+            _ => {}
+        }
     }
 
     // Done
     log
 }
 
-/// `O(g)` Pick a guard based on the given strategy
-pub fn choose<F>(log: &SleepLog, strategy: F) -> (usize, usize)
-where
-    F: Fn(&[u32]) -> u32,
-{
-    let (sleepiest_guard, _) = log
-        .iter()
-        .max_by_key(|(_, sleep)| strategy(sleep))
-        .expect(TRUST);
+/// A way to score how sleepy a guard is. Higher scores are sleepier.
+pub trait Strategy {
+    /// Score the given guard, who is known to have slept at least once
+    fn score(&self, log: &SleepLog, guard: usize) -> u64;
+}
+
+/// Any function of the guard's minutes is a strategy
+impl<F: Fn(&[u32]) -> u32> Strategy for F {
+    fn score(&self, log: &SleepLog, guard: usize) -> u64 {
+        u64::from(self(log.get(guard).expect(TRUST)))
+    }
+}
+
+/// Total minutes asleep in the window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Total;
+
+impl Strategy for Total {
+    fn score(&self, log: &SleepLog, guard: usize) -> u64 {
+        log.get(guard)
+            .expect(TRUST)
+            .iter()
+            .map(|&m| u64::from(m))
+            .sum()
+    }
+}
+
+/// Most times asleep in any single minute
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Peak;
+
+impl Strategy for Peak {
+    fn score(&self, log: &SleepLog, guard: usize) -> u64 {
+        u64::from(*log.get(guard).expect(TRUST).iter().max().expect(TRUST))
+    }
+}
+
+/// How concentrated the guard's sleep is in the same minutes, as the variance of their minute
+/// counts (scaled by the square of the number of minutes to keep it an integer)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Consistency;
+
+impl Strategy for Consistency {
+    fn score(&self, log: &SleepLog, guard: usize) -> u64 {
+        let minutes = log.get(guard).expect(TRUST);
+        let n = minutes.len() as u64;
+        let sum: u64 = minutes.iter().map(|&m| u64::from(m)).sum();
+        let squares: u64 = minutes.iter().map(|&m| u64::from(m) * u64::from(m)).sum();
+        n * squares - sum * sum
+    }
+}
+
+/// Minutes in the guard's longest continuous nap
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LongestStreak;
+
+impl Strategy for LongestStreak {
+    fn score(&self, log: &SleepLog, guard: usize) -> u64 {
+        log.shifts()
+            .iter()
+            .filter(|s| s.guard == guard)
+            .flat_map(|s| s.naps.iter())
+            .map(|n| u64::from(n.minutes()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A guard's place in a ranking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ranking {
+    /// The guard
+    pub guard: usize,
+    /// The strategy's score for the guard
+    pub score: u64,
+    /// The guard's sleepiest minute
+    pub minute: usize,
+    /// The times the guard slept in that minute
+    pub slept: u32,
+}
 
-    let (sleepiest_minute, _) = log
-        .get(sleepiest_guard)
-        .expect(TRUST)
+/// `O(g log g)` Rank the top `n` guards by the given strategy. Guards with equal scores are ranked
+/// by their identifier (lowest first), and each guard's sleepiest minute is the earliest of their
+/// most slept minutes.
+pub fn rank<S: Strategy + ?Sized>(log: &SleepLog, strategy: &S, n: usize) -> Vec<Ranking> {
+    let mut ranking: Vec<Ranking> = log
         .iter()
-        .enumerate()
-        .max_by_key(|(_, slept)| *slept)
-        .expect(TRUST);
+        .map(|(guard, minutes)| {
+            let earliest = |best: (usize, u32), (m, &s): (usize, &u32)| {
+                if s > best.1 {
+                    (m, s)
+                } else {
+                    best
+                }
+            };
+            let (minute, slept) = minutes.iter().enumerate().fold((0, 0), earliest);
+            Ranking {
+                guard,
+                score: strategy.score(log, guard),
+                minute,
+                slept,
+            }
+        }).collect();
+
+    ranking.sort_by(|a, b| b.score.cmp(&a.score).then(a.guard.cmp(&b.guard)));
+    ranking.truncate(n);
+    ranking
+}
 
-    (*sleepiest_guard, sleepiest_minute)
+/// `O(g log g)` Pick a guard based on the given strategy, returning the guard and their sleepiest
+/// minute. See [rank] for how ties are broken.
+pub fn choose<S: Strategy>(log: &SleepLog, strategy: S) -> (usize, usize) {
+    let top = rank(log, &strategy, 1);
+    let first = top.first().expect(TRUST);
+    (first.guard, first.minute)
 }
 
-/// `O(g)` Find the sleepiest minute of the sleepiest guard
+/// `O(g log g)` Find the sleepiest minute of the sleepiest guard
 pub fn part1(log: &SleepLog) -> (usize, usize) {
-    choose(log, |sleep| sleep.iter().sum())
+    choose(log, Total)
 }
 
-/// `O(g)` Find the guard who is found sleeping most at a particular minute
+/// `O(g log g)` Find the guard who is found sleeping most at a particular minute
 pub fn part2(log: &SleepLog) -> (usize, usize) {
-    choose(log, |sleep| *sleep.iter().max().expect(TRUST))
+    choose(log, Peak)
 }
 
-#[test]
-fn examples() {
-    let input = r"
+#[cfg(test)]
+const EXAMPLE: &str = r"
 [1518-11-01 00:00] Guard #10 begins shift
 [1518-11-01 00:05] falls asleep
 [1518-11-01 00:25] wakes up
@@ -101,7 +868,10 @@ fn examples() {
 [1518-11-05 00:45] falls asleep
 [1518-11-05 00:55] wakes up
 ";
-    let log = read(input);
+
+#[test]
+fn examples() {
+    let log = read(EXAMPLE);
     let (guard, minute) = part1(&log);
 
     assert_eq!(240, guard * minute);
@@ -110,9 +880,185 @@ fn examples() {
     assert_eq!(4455, guard * minute);
 }
 
+#[test]
+fn parsing() {
+    let event: Event = "[1518-11-01 23:58] Guard #99 begins shift".parse().unwrap();
+    assert_eq!(Kind::BeginShift(99), event.kind);
+    assert_eq!(
+        Time {
+            year: 1518,
+            month: 11,
+            day: 1,
+            hour: 23,
+            minute: 58
+        },
+        event.time
+    );
+    assert_eq!(Ok(Kind::WakesUp), "wakes up".parse());
+    assert_eq!(Ok(Kind::FallsAsleep), " falls asleep ".parse());
+
+    let invalid = |s: &str| s.parse::<Event>().unwrap_err();
+    assert_eq!(
+        ParseEventError::Kind(String::from("dozes off")),
+        invalid("[1518-11-01 00:05] dozes off")
+    );
+    assert_eq!(
+        ParseEventError::Kind(String::from("Guard #x begins shift")),
+        invalid("[1518-11-01 00:05] Guard #x begins shift")
+    );
+    assert_eq!(
+        ParseEventError::Time(String::from("1518-02-29 00:05")),
+        invalid("[1518-02-29 00:05] wakes up")
+    );
+    assert!("[1600-02-29 00:05] wakes up".parse::<Event>().is_ok());
+
+    // Sorted by time, not by text: "11-10" sorts before "11-9 " as text
+    let sorted =
+        events("[1518-11-10 00:40] falls asleep\n[1518-11-9 23:58] Guard #99 begins shift");
+    assert_eq!(Kind::BeginShift(99), sorted.unwrap()[0].kind);
+}
+
+#[test]
+fn anomalies() {
+    let input = r"
+[1518-11-01 00:00] falls asleep
+[1518-11-01 00:01] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:10] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:30] wakes up
+[1518-11-02 00:00] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+";
+    let anomalies = validate(&records(input).unwrap());
+    let found: Vec<(Option<usize>, usize, Problem)> = anomalies
+        .iter()
+        .map(|a| (a.guard, a.record.line, a.problem))
+        .collect();
+
+    assert_eq!(
+        vec![
+            (None, 2, Problem::NoGuard),
+            (Some(10), 5, Problem::AlreadyAsleep),
+            (Some(10), 7, Problem::NotAsleep),
+            (Some(99), 9, Problem::NeverWakes),
+        ],
+        found
+    );
+    assert_eq!(
+        "line 5: guard #10 falls asleep while already asleep",
+        anomalies[1].to_string()
+    );
+
+    assert_eq!(Err(LogError::Anomalies(anomalies)), read_strict(input));
+    assert_eq!(Some(&20), read(input).totals().get(&10));
+    assert!(read_strict("[1518-11-01 00:00] naps").is_err());
+}
+
+#[test]
+fn statistics() {
+    let log = read(EXAMPLE);
+
+    let totals: Vec<(usize, u32)> = log.totals().into_iter().collect();
+    assert_eq!(vec![(10, 50), (99, 30)], totals);
+
+    let lengths: Vec<(u32, usize)> = log.shift_lengths().into_iter().collect();
+    assert_eq!(vec![(55, 1), (57, 1), (58, 1), (60, 1), (62, 1)], lengths);
+
+    assert_eq!(Some((36, 2)), log.most_shared_minute());
+
+    let (guard, nap) = log.longest_nap().unwrap();
+    assert_eq!((10, 25), (guard, nap.minutes()));
+    assert_eq!("1518-11-01 00:30", nap.start.to_string());
+
+    assert_eq!(
+        r"Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########.....
+",
+        log.render()
+    );
+
+    let guard = log.render_guard(99);
+    assert_eq!(
+        Some("Total       ....................................1111222223222211111....."),
+        guard.lines().last()
+    );
+}
+
+#[test]
+fn windows() {
+    let input = r"
+[1518-11-01 22:00] Guard #7 begins shift
+[1518-11-01 23:50] falls asleep
+[1518-11-02 00:10] wakes up
+[1518-11-02 00:30] falls asleep
+[1518-11-02 02:30] wakes up
+";
+    // The midnight hour only sees the part of each nap after midnight
+    let hour = read(input);
+    assert_eq!(Some(&140), hour.totals().get(&7));
+    assert_eq!(Some(40), hour.get(7).map(|m| m.iter().sum()));
+    assert_eq!(180, hour.shifts()[0].length());
+
+    // Every minute of the day, with the first nap wrapping around midnight
+    let minutes = read_with(input, Window::day(0, 1).expect(TRUST));
+    let slept = minutes.get(7).unwrap();
+    assert_eq!(1440, slept.len());
+    assert_eq!(vec![1; 10], slept[1430..].to_vec());
+    assert_eq!(vec![1; 10], slept[..10].to_vec());
+    assert_eq!(140, slept.iter().sum::<u32>());
+
+    // Hourly buckets for a day starting at noon
+    let hours = read_with(input, Window::day(12 * 60, 60).expect(TRUST));
+    assert_eq!(None, Window::day(0, 0));
+    assert_eq!(None, Window::new(0, 0, 1));
+    assert_eq!(None, Window::new(0, 60, 0));
+    let early = Window::new(25 * 60, 4, 15).expect(TRUST);
+    assert_eq!(
+        (60, 4, 15),
+        (early.start(), early.buckets(), early.resolution())
+    );
+    let slept = hours.get(7).unwrap();
+    assert_eq!(vec![0, 10, 40, 60, 30, 0], slept[10..16].to_vec());
+    assert_eq!(840, hours.shifts()[0].length());
+    assert_eq!(Some((11, 1)), hours.most_shared_minute());
+    assert_eq!(
+        Some("11-01  #7  ...........####........."),
+        hours.render().lines().last()
+    );
+}
+
+#[test]
+fn strategies() {
+    let log = read(EXAMPLE);
+    let top = |strategy: &dyn Strategy| -> Vec<(usize, u64, usize)> {
+        rank(&log, strategy, 5)
+            .into_iter()
+            .map(|r| (r.guard, r.score, r.minute))
+            .collect()
+    };
+
+    assert_eq!(vec![(10, 50, 24), (99, 30, 45)], top(&Total));
+    assert_eq!(vec![(99, 3, 45), (10, 2, 24)], top(&Peak));
+    assert_eq!(vec![(10, 25, 24), (99, 10, 45)], top(&LongestStreak));
+    assert_eq!(vec![(99, 2340, 45), (10, 620, 24)], top(&Consistency));
+    assert_eq!(1, rank(&log, &Total, 1).len());
+
+    // Ties go to the lowest guard, and the earliest minute
+    let tied = |sleep: &[u32]| u32::from(!sleep.is_empty());
+    assert_eq!((10, 24), choose(&log, tied));
+}
+
 #[test]
 fn solution() {
     let log = read(include_str!("input/4"));
+    assert_eq!(Ok(&log), read_strict(include_str!("input/4")).as_ref());
     let (guard, minute) = part1(&log);
     assert_eq!(60438, guard * minute);
 
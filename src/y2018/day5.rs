@@ -3,6 +3,13 @@
 //! Alchemical Reduction ([Statement](https://adventofcode.com/2018/day/5)).
 //! Input size parameters `n`: Length of input, `m` number of reacting units
 
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::thread;
+
 use super::super::TRUST;
 
 /// ASCII case difference
@@ -17,61 +24,389 @@ pub fn inverses(x: u8, y: u8) -> bool {
     }
 }
 
-/// `O(n*m)` Reduce the given polymer to its smallest canceling inverse units
-pub fn part1(input: &[u8]) -> usize {
-    let mut last: Vec<u8> = Vec::from(input);
-    let mut current: Vec<u8> = Vec::with_capacity(last.len());
-
-    let mut reduced = true;
-
-    while reduced {
-        reduced = false;
-        let mut index = 0;
-
-        while index < last.len() {
-            let unit = last[index];
-            let next = if index + 1 < last.len() {
-                last[index + 1]
-            } else {
-                0
-            };
-
-            if inverses(unit, next) {
-                reduced = true;
-                index += 2;
-                continue;
-            }
+/// A rule that decides which adjacent units cancel each other
+pub trait Reacts<T> {
+    /// Check if unit `x` followed by unit `y` react
+    fn reacts(&self, x: &T, y: &T) -> bool;
+}
+
+/// Any predicate over a pair of units is a rule
+impl<T, F: Fn(&T, &T) -> bool> Reacts<T> for F {
+    fn reacts(&self, x: &T, y: &T) -> bool {
+        self(x, y)
+    }
+}
+
+/// The puzzle's rule: units react if they are the same ASCII letter in opposite case
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiCase;
+
+impl Reacts<u8> for AsciiCase {
+    fn reacts(&self, x: &u8, y: &u8) -> bool {
+        inverses(*x, *y)
+    }
+}
+
+/// Units react if they are the same letter in opposite case, for any Unicode letter with a simple
+/// one-to-one case mapping
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodeCase;
 
-            current.push(unit);
-            index += 1;
+impl Reacts<char> for UnicodeCase {
+    fn reacts(&self, x: &char, y: &char) -> bool {
+        let flipped = if x.is_lowercase() {
+            single(x.to_uppercase())
+        } else {
+            single(x.to_lowercase())
+        };
+        flipped.is_some_and(|f| f != *x && f == *y)
+    }
+}
+
+/// The only item of the iterator, if it has exactly one
+fn single<I: Iterator<Item = char>>(mut chars: I) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// A rule table of unit pairs that react when the first is followed by the second
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pairs<T: Eq + Hash> {
+    /// The units each unit reacts with when followed by them
+    table: HashMap<T, HashSet<T>>,
+}
+
+impl<T: Eq + Hash + Clone> Pairs<T> {
+    /// A table where the given pairs react in the given order
+    pub fn new<I: IntoIterator<Item = (T, T)>>(pairs: I) -> Pairs<T> {
+        let mut table: HashMap<T, HashSet<T>> = HashMap::new();
+        for (x, y) in pairs {
+            table.entry(x).or_default().insert(y);
         }
+        Pairs { table }
+    }
 
-        last.clear();
-        last.extend(current.drain(..))
+    /// Make every pair in the table react in either order
+    pub fn symmetric(self) -> Pairs<T> {
+        let pairs: Vec<(T, T)> = self
+            .table
+            .into_iter()
+            .flat_map(|(x, ys)| ys.into_iter().map(move |y| (x.clone(), y)))
+            .collect();
+        let reversed: Vec<(T, T)> = pairs.iter().map(|(x, y)| (y.clone(), x.clone())).collect();
+        Pairs::new(pairs.into_iter().chain(reversed))
     }
+}
 
-    last.len()
+impl<T: Eq + Hash + Clone> Reacts<T> for Pairs<T> {
+    fn reacts(&self, x: &T, y: &T) -> bool {
+        self.table.get(x).is_some_and(|ys| ys.contains(y))
+    }
 }
 
-/// `O(n*m)` Find the "problem" unit, i.e. the unit which when removed from the input gives the smallest reduction
+/// A pair list that is not a sequence of two-character pairs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePairsError(String);
+
+impl fmt::Display for ParsePairsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a pair of two characters, found `{}`", self.0)
+    }
+}
+
+impl Error for ParsePairsError {}
+
+/// Parse a pair list as given on the command line, e.g. `(),[],{}` for bracket matching or `AT CG`
+/// (made symmetric) for complementary bases. Pairs are separated by commas or whitespace.
+impl FromStr for Pairs<char> {
+    type Err = ParsePairsError;
+
+    fn from_str(input: &str) -> Result<Pairs<char>, ParsePairsError> {
+        let pairs = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(x), Some(y), None) => Ok((x, y)),
+                    _ => Err(ParsePairsError(s.to_string())),
+                }
+            }).collect::<Result<Vec<_>, _>>()?;
+        Ok(Pairs::new(pairs))
+    }
+}
+
+/// `O(n)` Reduce the given polymer to its smallest canceling inverse units
+pub fn part1(input: &[u8]) -> usize {
+    reduce(input).len()
+}
+
+/// `O(n*m)` Find the length of the smallest reduction after removing the "problem" unit
 pub fn part2(input: &str) -> usize {
-    (b'a'..=b'z')
-        .map(|u| (u as char, ((u - CASE) as char)))
-        .map(|(l, u)| part1(input.replace(l, "").replace(u, "").as_bytes()))
-        .min()
+    problem(input).1
+}
+
+/// `O(n*m)` Find the "problem" unit, i.e. the unit which when removed from the input gives the
+/// smallest reduction, and the length of that reduction. Removing a unit never stops any other pair
+/// from reacting, so each candidate starts from the already reduced polymer rather than the full
+/// input. Ties go to the earliest letter.
+pub fn problem(input: &str) -> (char, usize) {
+    candidates(input)
+        .into_iter()
+        .min_by_key(|&(_, length)| length)
         .expect(TRUST)
 }
 
+/// `O(n*m)` The length of the reduction after removing each unit, in alphabetical order
+pub fn candidates(input: &str) -> Vec<(char, usize)> {
+    let polymer = reduce(input.as_bytes());
+    (b'a'..=b'z').map(|l| without(&polymer, l)).collect()
+}
+
+/// `O(n*m/t)` Same as [candidates], but with the units split across `t` threads. The threads share
+/// the reduced polymer and filter out their unit as they reduce, so no candidate copies the input.
+pub fn par_candidates(input: &str) -> Vec<(char, usize)> {
+    let polymer = reduce(input.as_bytes());
+    let units: Vec<u8> = (b'a'..=b'z').collect();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = units.len().div_ceil(threads);
+
+    thread::scope(|scope| {
+        let polymer = &polymer;
+        let workers: Vec<_> = units
+            .chunks(chunk)
+            .map(|letters| {
+                scope.spawn(move || {
+                    letters
+                        .iter()
+                        .map(|&l| without(polymer, l))
+                        .collect::<Vec<_>>()
+                })
+            }).collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect(TRUST))
+            .collect()
+    })
+}
+
+/// `O(n)` Reduce the polymer with both cases of the given lowercase unit filtered out
+fn without(polymer: &[u8], l: u8) -> (char, usize) {
+    let u = l - CASE;
+    let units = polymer.iter().cloned().filter(|&x| x != l && x != u);
+    (l as char, reduce_with(units, &AsciiCase).len())
+}
+
+/// `O(n)` Reduce the given polymer in a single pass and return the units that remain
+pub fn reduce(input: &[u8]) -> Vec<u8> {
+    reduce_with(input.iter().cloned(), &AsciiCase)
+}
+
+/// `O(n)` React units as they arrive using the given rule. Each unit either cancels the last
+/// surviving unit on the stack or is pushed on to it, so the stack always holds a fully reduced
+/// prefix of the polymer.
+pub fn reduce_with<T, I, R>(units: I, rule: &R) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    R: Reacts<T> + ?Sized,
+{
+    let mut stack: Vec<T> = Vec::new();
+    for unit in units {
+        match stack.last() {
+            Some(last) if rule.reacts(last, &unit) => {
+                stack.pop();
+            }
+            _ => stack.push(unit),
+        }
+    }
+    stack
+}
+
+/// A single reaction between two units, identified by their positions in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reaction<T> {
+    /// The position of the left unit
+    pub left: usize,
+    /// The position of the right unit
+    pub right: usize,
+    /// The units that reacted
+    pub units: (T, T),
+}
+
+/// A record of every reaction in the order the reducer made them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace<T> {
+    /// The polymer before any reaction
+    pub input: Vec<T>,
+    /// The reactions in order
+    pub reactions: Vec<Reaction<T>>,
+}
+
+impl<T> Trace<T> {
+    /// Mark both units of the reaction as gone
+    fn react(alive: &mut [bool], reaction: &Reaction<T>) {
+        alive[reaction.left] = false;
+        alive[reaction.right] = false;
+    }
+
+    /// The input units still marked alive
+    fn survivors<'a>(&'a self, alive: &'a [bool]) -> impl Iterator<Item = &'a T> + 'a {
+        self.input
+            .iter()
+            .zip(alive.iter())
+            .filter(|(_, alive)| **alive)
+            .map(|(unit, _)| unit)
+    }
+}
+
+impl<T: Clone> Trace<T> {
+    /// The polymer after each reaction, starting with the input and ending with the reduced polymer
+    pub fn steps(&self) -> Vec<Vec<T>> {
+        let mut alive = vec![true; self.input.len()];
+        let mut steps = vec![self.input.clone()];
+        for reaction in self.reactions.iter() {
+            Trace::react(&mut alive, reaction);
+            steps.push(self.survivors(&alive).cloned().collect());
+        }
+        steps
+    }
+
+    /// `O(n)` The fully reduced polymer
+    pub fn reduced(&self) -> Vec<T> {
+        let mut alive = vec![true; self.input.len()];
+        for reaction in self.reactions.iter() {
+            Trace::react(&mut alive, reaction);
+        }
+        self.survivors(&alive).cloned().collect()
+    }
+}
+
+/// Show the polymer collapsing one reaction per line, with the pair that reacted and its positions.
+/// Only the current line is held in memory.
+impl fmt::Display for Trace<u8> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut alive = vec![true; self.input.len()];
+        let width = self.input.len();
+        writeln!(f, "{}", String::from_utf8_lossy(&self.input))?;
+        for reaction in self.reactions.iter() {
+            Trace::react(&mut alive, reaction);
+            let step: Vec<u8> = self.survivors(&alive).cloned().collect();
+            writeln!(
+                f,
+                "{:width$}  {}{} at {},{}",
+                String::from_utf8_lossy(&step),
+                reaction.units.0 as char,
+                reaction.units.1 as char,
+                reaction.left,
+                reaction.right,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `O(n)` Reduce the given polymer like [reduce] and record each reaction
+pub fn trace(input: &[u8]) -> Trace<u8> {
+    trace_with(input.iter().cloned(), &AsciiCase)
+}
+
+/// `O(n)` Reduce the given units like [reduce_with] and record each reaction
+pub fn trace_with<T, I, R>(units: I, rule: &R) -> Trace<T>
+where
+    T: Clone,
+    I: IntoIterator<Item = T>,
+    R: Reacts<T> + ?Sized,
+{
+    let input: Vec<T> = units.into_iter().collect();
+    let mut reactions = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    for (right, unit) in input.iter().enumerate() {
+        match stack.last() {
+            Some(&left) if rule.reacts(&input[left], unit) => {
+                stack.pop();
+                reactions.push(Reaction {
+                    left,
+                    right,
+                    units: (input[left].clone(), unit.clone()),
+                });
+            }
+            _ => stack.push(right),
+        }
+    }
+    Trace { input, reactions }
+}
+
 #[test]
 fn examples() {
     let input = "dabAcCaCBAcCcaDA";
     assert_eq!(10, part1(input.as_bytes()));
+    assert_eq!(b"dabCBAcaDA".to_vec(), reduce(input.as_bytes()));
+    assert!(reduce(b"abBA").is_empty());
+    assert_eq!(b"abAB".to_vec(), reduce(b"abAB"));
     assert_eq!(4, part2(input));
+    assert_eq!(('c', 4), problem(input));
+    assert_eq!(
+        vec![('a', 6), ('b', 8), ('c', 4), ('d', 6)],
+        candidates(input)[..4].to_vec()
+    );
+    assert_eq!(candidates(input), par_candidates(input));
+}
+
+#[test]
+fn traces() {
+    let input = "dabAcCaCBAcCcaDA";
+    let trace = trace(input.as_bytes());
+    assert_eq!(reduce(input.as_bytes()), trace.reduced());
+    assert_eq!(3, trace.reactions.len());
+    assert_eq!(
+        Reaction {
+            left: 4,
+            right: 5,
+            units: (b'c', b'C')
+        },
+        trace.reactions[0]
+    );
+    assert_eq!(
+        "dabAcCaCBAcCcaDA\n\
+         dabAaCBAcCcaDA    cC at 4,5\n\
+         dabCBAcCcaDA      Aa at 3,6\n\
+         dabCBAcaDA        cC at 10,11\n",
+        trace.to_string()
+    );
+}
+
+#[test]
+fn rules() {
+    let brackets: Pairs<char> = "(),[],{}".parse().unwrap();
+    assert!(reduce_with("([]{()})".chars(), &brackets).is_empty());
+    assert_eq!(vec![')', '('], reduce_with(")(".chars(), &brackets));
+
+    let bases = "AT CG".parse::<Pairs<char>>().unwrap().symmetric();
+    assert_eq!(vec!['A'], reduce_with("ACGTAAT".chars(), &bases));
+
+    assert!(reduce_with("aΣσA".chars(), &UnicodeCase).is_empty());
+    assert_eq!(vec!['ß', 'S'], reduce_with("ßS".chars(), &UnicodeCase));
+
+    let same = |x: &u8, y: &u8| x == y;
+    assert_eq!(
+        b"aba".to_vec(),
+        reduce_with(b"abbba".iter().cloned(), &same)
+    );
+
+    assert_eq!(
+        Err(ParsePairsError(String::from("abc"))),
+        "ab,abc".parse::<Pairs<char>>()
+    );
 }
 
 #[test]
 fn solution() {
     let input = include_str!("input/5");
     assert_eq!(11310, part1(input.as_bytes()));
-    assert_eq!(6020, part2(input))
+    assert_eq!(6020, part2(input));
+    assert_eq!(candidates(input), par_candidates(input));
 }